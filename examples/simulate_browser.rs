@@ -44,9 +44,8 @@ extern crate safe_core;
 extern crate safe_nfs;
 extern crate sodiumoxide;
 
-use routing::Data;
+use routing::{Data, StructuredData};
 
-const DEFAULT_SERVICE: &'static str = "www";
 const HOME_PAGE_FILE_NAME: &'static str = "index.html";
 
 fn handle_login() -> std::sync::Arc<std::sync::Mutex<safe_core::client::Client>> {
@@ -92,7 +91,7 @@ fn create_dns_record(client        : std::sync::Arc<std::sync::Mutex<safe_core::
                      dns_operations: &safe_dns::dns_operations::DnsOperations) -> Result<(), safe_dns::errors::DnsError> {
     println!("\n\n    Create Dns Record");
     println!(    "    =================");
-    println!("\nEnter Dns Name (eg., pepsico.com [Note: more than one \".\"s are not allowed in this simple example]):");
+    println!("\nEnter Dns Name (eg., pepsico.com or api.pepsico.com):");
     let mut long_name = String::new();
     let _ = std::io::stdin().read_line(&mut long_name);
     long_name = long_name.trim().to_string();
@@ -104,14 +103,15 @@ fn create_dns_record(client        : std::sync::Arc<std::sync::Mutex<safe_core::
 
     let owners = vec![try!(client.lock().unwrap().get_public_signing_key()).clone()];
     let secret_signing_key = try!(client.lock().unwrap().get_secret_signing_key()).clone();
-    let dns_struct_data = try!(dns_operations.register_dns(long_name,
-                                                           &public_messaging_encryption_key,
-                                                           &secret_messaging_encryption_key,
-                                                           &vec![],
-                                                           owners,
-                                                           &secret_signing_key,
-                                                           None));
-    Ok(try!(unwrap_result!(client.lock()).put(Data::Structured(dns_struct_data), None)))
+    let (dns_struct_data, auxiliary_struct_data) = try!(dns_operations.register_dns(long_name,
+                                                                                    &public_messaging_encryption_key,
+                                                                                    &secret_messaging_encryption_key,
+                                                                                    &vec![],
+                                                                                    owners,
+                                                                                    &secret_signing_key,
+                                                                                    None));
+    try!(unwrap_result!(client.lock()).put(Data::Structured(dns_struct_data), None));
+    store_auxiliary_struct_data(client, auxiliary_struct_data)
 }
 
 fn delete_dns_record(client        : std::sync::Arc<std::sync::Mutex<safe_core::client::Client>>,
@@ -127,8 +127,21 @@ fn delete_dns_record(client        : std::sync::Arc<std::sync::Mutex<safe_core::
 
     println!("Deleting Dns...");
 
-    let dns_struct_data = try!(dns_operations.delete_dns(&long_name, &secret_signing_key));
-    Ok(try!(unwrap_result!(client.lock()).delete(Data::Structured(dns_struct_data), None)))
+    let (dns_struct_data, auxiliary_struct_data) = try!(dns_operations.delete_dns(&long_name, &secret_signing_key));
+    try!(unwrap_result!(client.lock()).delete(Data::Structured(dns_struct_data), None));
+    store_auxiliary_struct_data(client, auxiliary_struct_data)
+}
+
+fn store_auxiliary_struct_data(client               : std::sync::Arc<std::sync::Mutex<safe_core::client::Client>>,
+                               auxiliary_struct_data: Vec<StructuredData>) -> Result<(), safe_dns::errors::DnsError> {
+    for struct_data in auxiliary_struct_data.into_iter() {
+        if struct_data.get_version() == 0 {
+            try!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        } else {
+            try!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        }
+    }
+    Ok(())
 }
 
 fn display_dns_records(dns_operations: &safe_dns::dns_operations::DnsOperations) -> Result<(), safe_dns::errors::DnsError> {
@@ -233,46 +246,41 @@ fn parse_url_and_get_home_page(client        : std::sync::Arc<std::sync::Mutex<s
                                dns_operations: &safe_dns::dns_operations::DnsOperations) -> Result<(), safe_dns::errors::DnsError> {
     println!("\n\n    Parse URL");
     println!(    "    =========");
-    println!("\nEnter SAFE-Url (eg., safe:lays.pepsico.com ie., \"safe:[<service-name>.]<dns-name>\"):");
+    println!("\nEnter SAFE-Url (eg., safe:lays.pepsico.com ie., \"safe:[<service-name>.]<dns-name>\" where <dns-name> may itself have several labels):");
     let mut url = String::new();
     let _ = std::io::stdin().read_line(&mut url);
     url = url.trim().to_string();
 
-    let re_with_service = try!(regex::Regex::new(r"safe:([^.]+?)\.([^.]+?\.[^.]+)$").map_err(|_| safe_dns::errors::DnsError::Unexpected("Failed to form Regular-Expression !!".to_string())));
-    let re_without_service = try!(regex::Regex::new(r"safe:([^.]+?\.[^.]+)$").map_err(|_| safe_dns::errors::DnsError::Unexpected("Failed to form Regular-Expression !!".to_string())));
-
-    let long_name;
-    let service_name;
-
-    if re_with_service.is_match(&url) {
-        let captures = try!(re_with_service.captures(&url).ok_or(safe_dns::errors::DnsError::Unexpected("Could not capture items in Url !!".to_string())));
-        let caps_0 = try!(captures.at(1).ok_or(safe_dns::errors::DnsError::Unexpected("Could not access a capture !!".to_string())));
-        let caps_1 = try!(captures.at(2).ok_or(safe_dns::errors::DnsError::Unexpected("Could not access a capture !!".to_string())));
-
-        long_name = caps_1.to_string();
-        service_name = caps_0.to_string();
-    } else if re_without_service.is_match(&url) {
-        let captures = try!(re_without_service.captures(&url).ok_or(safe_dns::errors::DnsError::Unexpected("Could not capture items in Url !!".to_string())));
-        let caps_0 = try!(captures.at(1).ok_or(safe_dns::errors::DnsError::Unexpected("Could not access a capture !!".to_string())));
-
-        long_name = caps_0.to_string();
-        service_name = DEFAULT_SERVICE.to_string();
-    } else {
-        return Err(safe_dns::errors::DnsError::Unexpected("Malformed Url !!".to_string()))
-    }
+    let re_url = try!(regex::Regex::new(r"^safe:(.+)$").map_err(|_| safe_dns::errors::DnsError::Unexpected("Failed to form Regular-Expression !!".to_string())));
+    let captures = try!(re_url.captures(&url).ok_or(safe_dns::errors::DnsError::Unexpected("Malformed Url !!".to_string())));
+    let host = try!(captures.at(1).ok_or(safe_dns::errors::DnsError::Unexpected("Could not access a capture !!".to_string())));
 
+    let resolution = try!(dns_operations.resolve_name(&host.to_string(), None));
     println!("Fetching data...");
 
-    let dir_key = try!(dns_operations.get_service_home_directory_key(&long_name, &service_name, None));
-    let directory_helper = safe_nfs::helper::directory_helper::DirectoryHelper::new(client.clone());
-    let dir_listing = try!(directory_helper.get(&dir_key));
-
-    let file = try!(dir_listing.get_files().iter().find(|a| *a.get_name() == HOME_PAGE_FILE_NAME.to_string())
-                                                       .ok_or(safe_dns::errors::DnsError::Unexpected("Could not find homepage !!".to_string())));
     let file_helper = safe_nfs::helper::file_helper::FileHelper::new(client.clone());
-    let mut reader = file_helper.read(file);
-    let size = reader.size();
-    let content = try!(reader.read(0, size));
+    let content = match try!(dns_operations.get_resolved_service_handle(&resolution,
+                                                                        resolution.get_service_name(),
+                                                                        safe_dns::dns_operations::MirrorSelection::PrimaryThenFallback,
+                                                                        None)) {
+        safe_dns::dns_operations::ServiceHandle::Directory(dir_listing) => {
+            let file = try!(dir_listing.get_files().iter().find(|a| *a.get_name() == HOME_PAGE_FILE_NAME.to_string())
+                                                               .ok_or(safe_dns::errors::DnsError::Unexpected("Could not find homepage !!".to_string())));
+            let mut reader = file_helper.read(file);
+            let size = reader.size();
+            try!(reader.read(0, size))
+        },
+        safe_dns::dns_operations::ServiceHandle::File(file) => {
+            let mut reader = file_helper.read(&file);
+            let size = reader.size();
+            try!(reader.read(0, size))
+        },
+        safe_dns::dns_operations::ServiceHandle::ImmutableData(data) => data.value().clone(),
+        safe_dns::dns_operations::ServiceHandle::Redirect(redirect_url) => {
+            println!("\n301 Moved Permanently -> {}", redirect_url);
+            return Ok(())
+        },
+    };
 
     println!("\n-----------------------------------------------------");
     println!(  "                 Home Page Contents");
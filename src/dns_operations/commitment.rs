@@ -0,0 +1,404 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use xor_name::XorName;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use super::{DataEncryptionKeys, DnsOperations, validation, MIN_REGISTERED_NAME_LABELS};
+use super::dns_record::CommitmentProof;
+use super::expiry::get_current_time;
+
+/// Tag of the structured data holding a commitment to register a Dns-name, or a claim of the name
+/// through such a commitment
+pub const COMMITMENT_TAG: u64 = 7;
+
+/// Recommended number of seconds that must pass between committing to a Dns-name and revealing it,
+/// see `DnsOperations::with_required_commitments`
+pub const MIN_COMMITMENT_AGE: u64 = 60;
+
+/// Length of the random salt hiding the committed Dns-name
+pub const COMMITMENT_SALT_LENGTH: usize = 32;
+
+/// Maximum number of claims of a Dns-name looked at during resolution
+pub const MAX_COMMITMENT_CLAIMS: u64 = 8;
+
+/// Content of the structured data holding a commitment
+#[derive(RustcEncodable, RustcDecodable)]
+struct Commitment {
+    committed_at: u64,
+}
+
+/// Content of the structured data claiming a Dns-name through a commitment
+#[derive(RustcEncodable, RustcDecodable)]
+struct Claim {
+    salt: Vec<u8>,
+}
+
+impl DnsOperations {
+    /// Get an instance which registers Dns-names in two phases, through `commit_dns_registration`
+    /// and `reveal_dns_registration`, with at least `min_commitment_age` seconds in between.
+    /// Such an instance only resolves names registered that way: out of the owners having revealed
+    /// the same name, the one with the earliest matured commitment is honoured, so that racing for
+    /// a name after seeing it revealed is of no use. The commitment times are as declared by the
+    /// committing clients, the network keeping no clock.
+    pub fn with_required_commitments(mut self, min_commitment_age: u64) -> DnsOperations {
+        self.min_commitment_age = Some(min_commitment_age);
+        self
+    }
+
+    /// First phase of registering a Dns-name without revealing it: get the structured data
+    /// committing to a salted hash of the name, to be put to the network, and the salt, to be kept
+    /// for `reveal_dns_registration`.
+    pub fn commit_dns_registration(&self,
+                                   long_name          : &String,
+                                   owners             : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                                   private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<(StructuredData, Vec<u8>), ::errors::DnsError> {
+        if self.min_commitment_age.is_none() {
+            return Err(::errors::DnsError::from("Instance does not register names through commitments"))
+        }
+        if try!(validation::split_name_labels(long_name)).len() < MIN_REGISTERED_NAME_LABELS {
+            return Err(::errors::DnsError::InvalidDnsName)
+        }
+
+        let salt = ::sodiumoxide::randombytes::randombytes(COMMITMENT_SALT_LENGTH);
+        let struct_data = try!(create_commitment(self, long_name, &salt, get_current_time(), owners, private_signing_key));
+        Ok((struct_data, salt))
+    }
+
+    /// Second phase of registering a Dns-name: same as `register_dns`, for a name committed to
+    /// through `commit_dns_registration` by the same owners, at least the minimum commitment age
+    /// earlier. The auxiliary structured data returned along with the Dns record starts with the
+    /// one claiming the name, which must be put to the network after the record.
+    /// Claims of a name take the first free of `MAX_COMMITMENT_CLAIMS` slots. Should putting the
+    /// claim fail because someone else took the slot in the meantime, the reveal can be repeated
+    /// with the same salt, the Dns record being in place already.
+    pub fn reveal_dns_registration(&self,
+                                   long_name                      : String,
+                                   salt                           : Vec<u8>,
+                                   public_messaging_encryption_key: &::sodiumoxide::crypto::box_::PublicKey,
+                                   secret_messaging_encryption_key: &::sodiumoxide::crypto::box_::SecretKey,
+                                   services                       : &Vec<(String, ::safe_nfs::metadata::directory_key::DirectoryKey)>,
+                                   owners                         : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                                   private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                                   data_encryption_keys           : DataEncryptionKeys) -> Result<(StructuredData,
+                                                                                                   Vec<StructuredData>), ::errors::DnsError> {
+        let min_commitment_age = try!(self.min_commitment_age
+                                          .ok_or(::errors::DnsError::from("Instance does not register names through commitments")));
+        let commitment_identifier = get_commitment_identifier(&self.namespace, &long_name, &salt);
+        let committed_at = try!(get_commitment_time(self, &commitment_identifier, &owners));
+        if committed_at.saturating_add(min_commitment_age) > get_current_time() {
+            return Err(::errors::DnsError::CommitmentNotMatured)
+        }
+        let claim_identifier = try!(get_free_claim_identifier(self, &long_name));
+
+        let proof = CommitmentProof {
+            salt: salt.clone(),
+        };
+        let (struct_data, auxiliary_struct_data) = try!(self.register_dns_impl(long_name,
+                                                                               get_committed_record_identifier(&commitment_identifier, &salt),
+                                                                               public_messaging_encryption_key,
+                                                                               secret_messaging_encryption_key,
+                                                                               services,
+                                                                               owners.clone(),
+                                                                               Some(proof),
+                                                                               private_signing_key,
+                                                                               data_encryption_keys));
+
+        let claim = Claim {
+            salt: salt,
+        };
+        debug!("Claiming dns through commitment ...");
+        let claim_struct_data = try!(super::dry_run::create_tagged_structured_data(self,
+                                                                                   COMMITMENT_TAG,
+                                                                                   claim_identifier,
+                                                                                   0,
+                                                                                   try!(serialise(&claim)),
+                                                                                   owners,
+                                                                                   vec![],
+                                                                                   private_signing_key,
+                                                                                   None));
+        let mut claims_and_auxiliary_struct_data = vec![claim_struct_data];
+        claims_and_auxiliary_struct_data.extend(auxiliary_struct_data);
+        Ok((struct_data, claims_and_auxiliary_struct_data))
+    }
+}
+
+/// Structured data housing the Dns record honoured for the given name: out of the records claimed
+/// through commitments, the one with the earliest matured commitment. Claims which do not check
+/// out are skipped, as anyone can take a free slot. `DnsRecordNotFound` if there is none.
+pub fn get_honoured_structured_data(dns_operations: &DnsOperations, long_name: &String) -> Result<StructuredData, ::errors::DnsError> {
+    let min_commitment_age = dns_operations.min_commitment_age.unwrap_or(0);
+    let now = get_current_time();
+
+    let mut honoured: Option<(u64, StructuredData)> = None;
+    for index in 0..MAX_COMMITMENT_CLAIMS {
+        let claim_struct_data = match dns_operations.get_tagged_structured_data(get_claim_identifier(&dns_operations.namespace, long_name, index), COMMITMENT_TAG) {
+            Ok(struct_data) => struct_data,
+            Err(ref error) if super::storage::is_data_not_found(error) => break,
+            Err(error) => return Err(error),
+        };
+        let (committed_at, struct_data) = match try!(verify_claim(dns_operations, long_name, &claim_struct_data)) {
+            Some(verified_claim) => verified_claim,
+            None => continue,
+        };
+        if committed_at.saturating_add(min_commitment_age) > now {
+            continue
+        }
+        if honoured.as_ref().map_or(true, |&(earliest, _)| committed_at < earliest) {
+            honoured = Some((committed_at, struct_data));
+        }
+    }
+
+    honoured.map(|(_, struct_data)| struct_data).ok_or(::errors::DnsError::DnsRecordNotFound)
+}
+
+/// Commitment time and structured data housing the Dns record of a claim, `None` if the claim does
+/// not check out or the record was deleted
+fn verify_claim(dns_operations   : &DnsOperations,
+                long_name        : &String,
+                claim_struct_data: &StructuredData) -> Result<Option<(u64, StructuredData)>, ::errors::DnsError> {
+    let content = try!(dns_operations.storage.get_structured_data_content(claim_struct_data, None));
+    let claim: Claim = match deserialise(&content) {
+        Ok(claim) => claim,
+        Err(_) => return Ok(None),
+    };
+
+    let commitment_identifier = get_commitment_identifier(&dns_operations.namespace, long_name, &claim.salt);
+    let committed_at = match get_commitment_time(dns_operations, &commitment_identifier, claim_struct_data.get_owner_keys()) {
+        Ok(committed_at) => committed_at,
+        Err(::errors::DnsError::InvalidCommitment) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    match dns_operations.get_structured_data(get_committed_record_identifier(&commitment_identifier, &claim.salt)) {
+        Ok(struct_data) => Ok(if struct_data.get_data().is_empty() {
+            None
+        } else {
+            Some((committed_at, struct_data))
+        }),
+        Err(ref error) if super::storage::is_data_not_found(error) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Identifier of the first slot not yet taken by a claim of the given name
+fn get_free_claim_identifier(dns_operations: &DnsOperations, long_name: &String) -> Result<XorName, ::errors::DnsError> {
+    for index in 0..MAX_COMMITMENT_CLAIMS {
+        let identifier = get_claim_identifier(&dns_operations.namespace, long_name, index);
+        match dns_operations.get_tagged_structured_data(identifier.clone(), COMMITMENT_TAG) {
+            Ok(_) => (),
+            Err(ref error) if super::storage::is_data_not_found(error) => return Ok(identifier),
+            Err(error) => return Err(error),
+        }
+    }
+    Err(::errors::DnsError::CommitmentClaimsFull)
+}
+
+/// Structured data committing to the given Dns-name at the given time
+fn create_commitment(dns_operations     : &DnsOperations,
+                     long_name          : &String,
+                     salt               : &Vec<u8>,
+                     committed_at       : u64,
+                     owners             : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                     private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<StructuredData, ::errors::DnsError> {
+    let commitment = Commitment {
+        committed_at: committed_at,
+    };
+
+    debug!("Committing to register a dns ...");
+    super::dry_run::create_tagged_structured_data(dns_operations,
+                                                  COMMITMENT_TAG,
+                                                  get_commitment_identifier(&dns_operations.namespace, long_name, salt),
+                                                  0,
+                                                  try!(serialise(&commitment)),
+                                                  owners,
+                                                  vec![],
+                                                  private_signing_key,
+                                                  None)
+}
+
+/// Time of the commitment found under the given identifier, which must be owned by the given
+/// owners
+fn get_commitment_time(dns_operations: &DnsOperations,
+                       identifier    : &XorName,
+                       owners        : &Vec<::sodiumoxide::crypto::sign::PublicKey>) -> Result<u64, ::errors::DnsError> {
+    let struct_data = match dns_operations.get_tagged_structured_data(identifier.clone(), COMMITMENT_TAG) {
+        Ok(struct_data) => struct_data,
+        Err(ref error) if super::storage::is_data_not_found(error) => return Err(::errors::DnsError::InvalidCommitment),
+        Err(error) => return Err(error),
+    };
+    if *struct_data.get_owner_keys() != *owners {
+        return Err(::errors::DnsError::InvalidCommitment)
+    }
+
+    let content = try!(dns_operations.storage.get_structured_data_content(&struct_data, None));
+    match deserialise::<Commitment>(&content) {
+        Ok(commitment) => Ok(commitment.committed_at),
+        Err(_) => Err(::errors::DnsError::InvalidCommitment),
+    }
+}
+
+/// Identifier of the structured data holding the commitment to the given Dns-name
+fn get_commitment_identifier(namespace: &super::Namespace, long_name: &String, salt: &Vec<u8>) -> XorName {
+    let mut salted_name = salt.clone();
+    salted_name.extend(long_name.as_bytes().iter().cloned());
+    namespace.get_identifier(&salted_name)
+}
+
+/// Identifier of the structured data housing the Dns record registered through the given
+/// commitment. The salt is part of it, so that the identifier is not known before the reveal.
+fn get_committed_record_identifier(commitment_identifier: &XorName, salt: &Vec<u8>) -> XorName {
+    let mut content = commitment_identifier.0.to_vec();
+    content.extend(salt.iter().cloned());
+    XorName::new(::sodiumoxide::crypto::hash::sha512::hash(&content).0)
+}
+
+/// Identifier of the given slot for claims of the Dns-name
+fn get_claim_identifier(namespace: &super::Namespace, long_name: &String, index: u64) -> XorName {
+    namespace.get_identifier(format!("{}#claim-{}", long_name, index).as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use dns_operations::DnsOperations;
+    use dns_operations::expiry::get_current_time;
+    use dns_operations::test_utils;
+
+    #[test]
+    fn reveal_requires_matured_commitment() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone())).with_required_commitments(3600);
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        // Registering without a commitment
+        match dns_operations.register_dns(dns_name.clone(),
+                                          &messaging_keypair.0,
+                                          &messaging_keypair.1,
+                                          &vec![],
+                                          owners.clone(),
+                                          &secret_signing_key,
+                                          None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::InvalidCommitment) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+
+        // Revealing without a commitment
+        match dns_operations.reveal_dns_registration(dns_name.clone(),
+                                                     vec![0; super::COMMITMENT_SALT_LENGTH],
+                                                     &messaging_keypair.0,
+                                                     &messaging_keypair.1,
+                                                     &vec![],
+                                                     owners.clone(),
+                                                     &secret_signing_key,
+                                                     None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::InvalidCommitment) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+
+        let (struct_data, salt) = unwrap_result!(dns_operations.commit_dns_registration(&dns_name, owners.clone(), &secret_signing_key));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        // Revealing straight away
+        match dns_operations.reveal_dns_registration(dns_name.clone(),
+                                                     salt,
+                                                     &messaging_keypair.0,
+                                                     &messaging_keypair.1,
+                                                     &vec![],
+                                                     owners,
+                                                     &secret_signing_key,
+                                                     None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::CommitmentNotMatured) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+        assert!(!unwrap_result!(dns_operations.get_all_registered_names()).contains(&dns_name));
+    }
+
+    #[test]
+    fn resolve_earliest_commitment() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone())).with_required_commitments(0);
+        let rival_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let rival_dns_operations = unwrap_result!(DnsOperations::new(rival_client.clone())).with_required_commitments(0);
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+        let rival_owners = vec![unwrap_result!(unwrap_result!(rival_client.lock()).get_public_signing_key()).clone()];
+        let rival_secret_signing_key = unwrap_result!(unwrap_result!(rival_client.lock()).get_secret_signing_key()).clone();
+
+        // Committed a minute ago
+        let salt = ::sodiumoxide::randombytes::randombytes(super::COMMITMENT_SALT_LENGTH);
+        let struct_data = unwrap_result!(super::create_commitment(&dns_operations,
+                                                                  &dns_name,
+                                                                  &salt,
+                                                                  get_current_time() - 60,
+                                                                  owners.clone(),
+                                                                  &secret_signing_key));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        // Rival commits to the name and reveals it first
+        let (struct_data, rival_salt) = unwrap_result!(rival_dns_operations.commit_dns_registration(&dns_name,
+                                                                                                    rival_owners.clone(),
+                                                                                                    &rival_secret_signing_key));
+        unwrap_result!(unwrap_result!(rival_client.lock()).put(Data::Structured(struct_data), None));
+        let (rival_struct_data, auxiliary_struct_data) = unwrap_result!(rival_dns_operations.reveal_dns_registration(dns_name.clone(),
+                                                                                                                     rival_salt,
+                                                                                                                     &messaging_keypair.0,
+                                                                                                                     &messaging_keypair.1,
+                                                                                                                     &vec![],
+                                                                                                                     rival_owners,
+                                                                                                                     &rival_secret_signing_key,
+                                                                                                                     None));
+        let rival_identifier = rival_struct_data.get_identifier().clone();
+        unwrap_result!(unwrap_result!(rival_client.lock()).put(Data::Structured(rival_struct_data), None));
+        test_utils::publish_auxiliary_structured_data(&rival_dns_operations, auxiliary_struct_data);
+        assert_eq!(unwrap_result!(dns_operations.resolve_name(&dns_name, None)).identifier, rival_identifier);
+
+        // The earlier commitment is honoured once revealed
+        let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.reveal_dns_registration(dns_name.clone(),
+                                                                                                         salt,
+                                                                                                         &messaging_keypair.0,
+                                                                                                         &messaging_keypair.1,
+                                                                                                         &vec![],
+                                                                                                         owners,
+                                                                                                         &secret_signing_key,
+                                                                                                         None));
+        let identifier = struct_data.get_identifier().clone();
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+        assert_eq!(unwrap_result!(dns_operations.resolve_name(&dns_name, None)).identifier, identifier);
+        assert_eq!(unwrap_result!(rival_dns_operations.resolve_name(&dns_name, None)).identifier, identifier);
+
+        // A name registered without a commitment is not resolved
+        let uncommitted_dns_operations = unwrap_result!(DnsOperations::new(client));
+        let (other_dns_name, _) = test_utils::register_test_name(&uncommitted_dns_operations);
+        match dns_operations.resolve_name(&other_dns_name, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::DnsRecordNotFound) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+}
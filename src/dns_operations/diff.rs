@@ -0,0 +1,279 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use std::collections::{BTreeMap, HashMap};
+use routing::StructuredData;
+use super::{DataEncryptionKeys, DnsOperations};
+use super::dns_record::{Delegation, Dns, Service, ServiceInfo, ServiceLink, ServiceTarget};
+
+/// How a single value differs between two states
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Change<T> {
+    /// Only present in the new state
+    Added(T),
+    /// Only present in the old state
+    Removed(T),
+    /// Present in both states with different values
+    Modified {
+        /// Value in the old state
+        old: T,
+        /// Value in the new state
+        new: T,
+    },
+}
+
+/// How a service differs between two Dns records. A service that was added (or removed) has its
+/// `targets` marked `Added` (or `Removed`); `Modified` targets mean the service was retargeted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ServiceDiff {
+    /// Change of the targets (mirrors, primary first)
+    pub targets: Option<Change<Vec<ServiceTarget>>>,
+    /// Change of the metadata
+    pub info   : Option<Change<ServiceInfo>>,
+}
+
+/// Structured diff of two Dns records. Only what differs is listed; the kept history, the
+/// commitment proof and the forwarding left by a migration are not compared.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DnsDiff {
+    /// Changed services, by name
+    pub services        : BTreeMap<String, ServiceDiff>,
+    /// Change of the messaging encryption key
+    pub encryption_key  : Option<Change<::sodiumoxide::crypto::box_::PublicKey>>,
+    /// Changed delegations, by sub-label
+    pub delegations     : BTreeMap<String, Change<Delegation>>,
+    /// Change of the alias
+    pub alias           : Option<Change<String>>,
+    /// Changed text records, by key
+    pub text_records    : BTreeMap<String, Change<String>>,
+    /// Change of the default service
+    pub default_service : Option<Change<String>>,
+    /// Change of the wildcard service
+    pub wildcard_service: Option<ServiceDiff>,
+    /// Changed links to separately housed services, by name. Changes to the linked services
+    /// themselves are not part of the Dns record.
+    pub service_links   : BTreeMap<String, Change<ServiceLink>>,
+    /// Change of the expiry time
+    pub expires_at      : Option<Change<u64>>,
+}
+
+impl DnsDiff {
+    /// Diff going from `old` to `new`
+    pub fn between(old: &Dns, new: &Dns) -> DnsDiff {
+        diff_records(Some(old), Some(new))
+    }
+
+    /// True if both records are the same
+    pub fn is_empty(&self) -> bool {
+        self.services.is_empty() &&
+        self.encryption_key.is_none() &&
+        self.delegations.is_empty() &&
+        self.alias.is_none() &&
+        self.text_records.is_empty() &&
+        self.default_service.is_none() &&
+        self.wildcard_service.is_none() &&
+        self.service_links.is_empty() &&
+        self.expires_at.is_none()
+    }
+}
+
+/// Diff between a prepared structured data and the one currently published on the network
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StructuredDataDiff {
+    /// Version of the published structured data, `None` if nothing is published yet
+    pub published_version: Option<u64>,
+    /// Version of the prepared structured data
+    pub prepared_version : u64,
+    /// Change of the owners
+    pub owners           : Option<Change<Vec<::sodiumoxide::crypto::sign::PublicKey>>>,
+    /// Diff of the Dns records carried. A record that is not published yet (or deleted by the
+    /// prepared structured data) is diffed as if it were empty.
+    pub dns              : DnsDiff,
+}
+
+impl DnsOperations {
+    /// Diff the structured data returned by any of the mutating operations (before it is put,
+    /// posted or deleted) against the one currently published, eg., for it to be reviewed.
+    pub fn diff_against_published(&self,
+                                  prepared            : &StructuredData,
+                                  data_decryption_keys: DataEncryptionKeys) -> Result<StructuredDataDiff, ::errors::DnsError> {
+        let published = match self.get_structured_data(prepared.get_identifier().clone()) {
+            Ok(struct_data) => Some(struct_data),
+            Err(ref error) if super::storage::is_data_not_found(error) => None,
+            Err(error) => return Err(error),
+        };
+        let published_dns_record = match published {
+            Some(ref struct_data) => try!(self.decode_dns_record(struct_data, data_decryption_keys)),
+            None => None,
+        };
+        let prepared_dns_record = try!(self.decode_dns_record(prepared, data_decryption_keys));
+
+        Ok(StructuredDataDiff {
+            published_version: published.as_ref().map(|struct_data| struct_data.get_version()),
+            prepared_version : prepared.get_version(),
+            owners           : diff_value(published.as_ref().map(|struct_data| struct_data.get_owner_keys()),
+                                          Some(prepared.get_owner_keys())),
+            dns              : diff_records(published_dns_record.as_ref(), prepared_dns_record.as_ref()),
+        })
+    }
+
+    /// Dns record carried by the given structured data, `None` if it carries none (ie., deleted)
+    fn decode_dns_record(&self,
+                         struct_data         : &StructuredData,
+                         data_decryption_keys: DataEncryptionKeys) -> Result<Option<Dns>, ::errors::DnsError> {
+        if struct_data.get_data().is_empty() {
+            return Ok(None)
+        }
+        Ok(Some(try!(self.read_dns_record(struct_data, data_decryption_keys))))
+    }
+}
+
+fn diff_records(old: Option<&Dns>, new: Option<&Dns>) -> DnsDiff {
+    DnsDiff {
+        services        : diff_maps(old.map(|dns| &dns.services), new.map(|dns| &dns.services), diff_service),
+        encryption_key  : diff_value(old.map(|dns| &dns.encryption_key), new.map(|dns| &dns.encryption_key)),
+        delegations     : diff_maps(old.map(|dns| &dns.delegations), new.map(|dns| &dns.delegations), diff_value),
+        alias           : diff_value(old.and_then(|dns| dns.alias.as_ref()), new.and_then(|dns| dns.alias.as_ref())),
+        text_records    : diff_maps(old.map(|dns| &dns.text_records), new.map(|dns| &dns.text_records), diff_value),
+        default_service : diff_value(old.and_then(|dns| dns.default_service.as_ref()),
+                                     new.and_then(|dns| dns.default_service.as_ref())),
+        wildcard_service: diff_service(old.and_then(|dns| dns.wildcard_service.as_ref()),
+                                       new.and_then(|dns| dns.wildcard_service.as_ref())),
+        service_links   : diff_maps(old.map(|dns| &dns.service_links), new.map(|dns| &dns.service_links), diff_value),
+        expires_at      : diff_value(old.and_then(|dns| dns.expires_at.as_ref()), new.and_then(|dns| dns.expires_at.as_ref())),
+    }
+}
+
+fn diff_service(old: Option<&Service>, new: Option<&Service>) -> Option<ServiceDiff> {
+    let diff = ServiceDiff {
+        targets: diff_value(old.map(|service| &service.targets), new.map(|service| &service.targets)),
+        info   : diff_value(old.and_then(|service| service.info.as_ref()), new.and_then(|service| service.info.as_ref())),
+    };
+
+    if diff.targets.is_none() && diff.info.is_none() {
+        None
+    } else {
+        Some(diff)
+    }
+}
+
+fn diff_maps<T, D, F>(old   : Option<&HashMap<String, T>>,
+                      new   : Option<&HashMap<String, T>>,
+                      differ: F) -> BTreeMap<String, D> where F: Fn(Option<&T>, Option<&T>) -> Option<D> {
+    let empty = HashMap::new();
+    let old = old.unwrap_or(&empty);
+    let new = new.unwrap_or(&empty);
+
+    old.keys()
+       .chain(new.keys())
+       .filter_map(|key| differ(old.get(key), new.get(key)).map(|diff| (key.clone(), diff)))
+       .collect()
+}
+
+fn diff_value<T: Clone + PartialEq>(old: Option<&T>, new: Option<&T>) -> Option<Change<T>> {
+    match (old, new) {
+        (None, None) => None,
+        (None, Some(new)) => Some(Change::Added(new.clone())),
+        (Some(old), None) => Some(Change::Removed(old.clone())),
+        (Some(old), Some(new)) => if old == new {
+            None
+        } else {
+            Some(Change::Modified {
+                old: old.clone(),
+                new: new.clone(),
+            })
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use xor_name::XorName;
+    use dns_operations::dns_record::{Dns, Service, ServiceKind, ServiceInfo, ServiceTarget};
+
+    #[test]
+    fn diff_dns_records() {
+        let directory = |seed| {
+            ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([seed; 64]),
+                                                                   15000,
+                                                                   false,
+                                                                   ::safe_nfs::AccessLevel::Public)
+        };
+
+        let mut old = Dns {
+            long_name        : "example.com".to_string(),
+            services         : ::std::collections::HashMap::new(),
+            encryption_key   : ::sodiumoxide::crypto::box_::gen_keypair().0,
+            delegations      : ::std::collections::HashMap::new(),
+            alias            : None,
+            text_records     : ::std::collections::HashMap::new(),
+            default_service  : None,
+            wildcard_service : None,
+            history          : None,
+            services_overflow: Vec::new(),
+            service_links    : ::std::collections::HashMap::new(),
+            expires_at       : None,
+            commitment       : None,
+            forwarded_to     : None,
+        };
+        let _ = old.services.insert("www".to_string(), Service::new(ServiceTarget::Directory(directory(1))));
+        let _ = old.services.insert("blog".to_string(), Service::new(ServiceTarget::Directory(directory(2))));
+        let _ = old.text_records.insert("contact".to_string(), "me".to_string());
+
+        assert!(DnsDiff::between(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        new.encryption_key = ::sodiumoxide::crypto::box_::gen_keypair().0;
+        let _ = new.services.remove("blog");
+        let _ = new.services.insert("api".to_string(), Service::new(ServiceTarget::Directory(directory(3))));
+        unwrap_option!(new.services.get_mut("www"), "").targets = vec![ServiceTarget::Directory(directory(4))];
+        unwrap_option!(new.services.get_mut("www"), "").info = Some(ServiceInfo::new(ServiceKind::Website));
+        let _ = new.text_records.insert("contact".to_string(), "you".to_string());
+        new.default_service = Some("www".to_string());
+
+        let diff = DnsDiff::between(&old, &new);
+        assert_eq!(diff.services.len(), 3);
+        assert_eq!(diff.services["blog"].targets,
+                   Some(Change::Removed(vec![ServiceTarget::Directory(directory(2))])));
+        assert_eq!(diff.services["api"].targets,
+                   Some(Change::Added(vec![ServiceTarget::Directory(directory(3))])));
+        assert_eq!(diff.services["www"],
+                   ServiceDiff {
+                       targets: Some(Change::Modified {
+                           old: vec![ServiceTarget::Directory(directory(1))],
+                           new: vec![ServiceTarget::Directory(directory(4))],
+                       }),
+                       info   : Some(Change::Added(ServiceInfo::new(ServiceKind::Website))),
+                   });
+        assert_eq!(diff.encryption_key,
+                   Some(Change::Modified {
+                       old: old.encryption_key,
+                       new: new.encryption_key,
+                   }));
+        assert_eq!(diff.text_records["contact"],
+                   Change::Modified {
+                       old: "me".to_string(),
+                       new: "you".to_string(),
+                   });
+        assert_eq!(diff.default_service, Some(Change::Added("www".to_string())));
+        assert!(diff.delegations.is_empty());
+        assert!(diff.alias.is_none());
+        assert!(diff.wildcard_service.is_none());
+        assert!(diff.service_links.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use super::{DataEncryptionKeys, DnsOperations};
+use super::dns_record::{Dns, Service, ServiceTarget};
+
+/// Label under which the wildcard service of a Dns-name is reported by
+/// `find_services_referencing_directory`
+pub const WILDCARD_SERVICE_LABEL: &'static str = "*";
+
+impl DnsOperations {
+    /// Get every (Dns-name, service) of the names registered by this user with a target in the
+    /// given directory, either as the home directory or as the directory of a file. The wildcard
+    /// service is reported as `WILDCARD_SERVICE_LABEL`. Linked services are not looked into as
+    /// they are not the user's to change.
+    pub fn find_services_referencing_directory(&self,
+                                               directory_key       : &::safe_nfs::metadata::directory_key::DirectoryKey,
+                                               data_decryption_keys: DataEncryptionKeys) -> Result<Vec<(String, String)>, ::errors::DnsError> {
+        let mut references = Vec::new();
+        for config in try!(super::dry_run::get_dns_configuration(self)).into_iter() {
+            let (_, dns_record) = try!(self.get_housing_structured_data_and_dns_record(&config.long_name, data_decryption_keys));
+            let mut service_names = get_services_referencing_directory(&dns_record, directory_key);
+            service_names.sort();
+            references.extend(service_names.into_iter().map(|service_name| (config.long_name.clone(), service_name)));
+        }
+
+        Ok(references)
+    }
+
+    /// Point every target of the names registered by this user that is in `old_directory_key` to
+    /// `new_directory_key` instead (see `find_services_referencing_directory`). One structured
+    /// data is returned for each Dns-name that changed, to be posted by the caller.
+    pub fn retarget_directory(&self,
+                              old_directory_key              : &::safe_nfs::metadata::directory_key::DirectoryKey,
+                              new_directory_key              : &::safe_nfs::metadata::directory_key::DirectoryKey,
+                              private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_decryption_keys: DataEncryptionKeys) -> Result<Vec<StructuredData>, ::errors::DnsError> {
+        let mut long_names: Vec<String> = try!(self.find_services_referencing_directory(old_directory_key, data_encryption_decryption_keys))
+                                              .into_iter()
+                                              .map(|(long_name, _)| long_name)
+                                              .collect();
+        long_names.dedup();
+
+        let mut struct_data_vec = Vec::with_capacity(long_names.len());
+        for long_name in long_names.iter() {
+            struct_data_vec.push(try!(self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+                debug!("Retargeting services of {:?} dns ...", long_name);
+                for service in dns_record.services.values_mut().chain(dns_record.wildcard_service.iter_mut()) {
+                    retarget_service(service, old_directory_key, new_directory_key);
+                }
+                Ok(())
+            })));
+        }
+
+        Ok(struct_data_vec)
+    }
+}
+
+fn get_services_referencing_directory(dns_record   : &Dns,
+                                      directory_key: &::safe_nfs::metadata::directory_key::DirectoryKey) -> Vec<String> {
+    let mut service_names: Vec<String> = dns_record.services
+                                                   .iter()
+                                                   .filter(|&(_, service)| references_directory(service, directory_key))
+                                                   .map(|(service_name, _)| service_name.clone())
+                                                   .collect();
+    if let Some(ref service) = dns_record.wildcard_service {
+        if references_directory(service, directory_key) {
+            service_names.push(WILDCARD_SERVICE_LABEL.to_string());
+        }
+    }
+    service_names
+}
+
+fn references_directory(service: &Service, directory_key: &::safe_nfs::metadata::directory_key::DirectoryKey) -> bool {
+    service.targets.iter().any(|target| match *target {
+        ServiceTarget::Directory(ref key) |
+        ServiceTarget::File { directory: ref key, .. } => *key == *directory_key,
+        _ => false,
+    })
+}
+
+/// Move the targets of the service in the old directory to the new one. A target ending up the
+/// same as an earlier mirror is dropped rather than kept twice.
+fn retarget_service(service          : &mut Service,
+                    old_directory_key: &::safe_nfs::metadata::directory_key::DirectoryKey,
+                    new_directory_key: &::safe_nfs::metadata::directory_key::DirectoryKey) {
+    let mut targets: Vec<ServiceTarget> = Vec::with_capacity(service.targets.len());
+    for target in service.targets.drain(..) {
+        let target = match target {
+            ServiceTarget::Directory(ref key) if *key == *old_directory_key => ServiceTarget::Directory(new_directory_key.clone()),
+            ServiceTarget::File { ref directory, ref file_name } if *directory == *old_directory_key => ServiceTarget::File {
+                directory: new_directory_key.clone(),
+                file_name: file_name.clone(),
+            },
+            target => target,
+        };
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    service.targets = targets;
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use xor_name::XorName;
+    use dns_operations::{DnsOperations, ServiceTarget};
+    use dns_operations::test_utils;
+
+    #[test]
+    fn find_and_retarget_directory_references() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+        let (dns_name, keys) = test_utils::register_test_name(&dns_operations);
+        let secret_signing_key = keys.secret_signing_key;
+
+        let old_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([1; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+        let new_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([2; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+        let other_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([3; 64]),
+                                                                                   15000,
+                                                                                   false,
+                                                                                   ::safe_nfs::AccessLevel::Public);
+
+        let favicon = ServiceTarget::File {
+            directory: old_dir_key.clone(),
+            file_name: "favicon.ico".to_string(),
+        };
+        let services = vec![("www".to_string(), ServiceTarget::Directory(old_dir_key.clone())),
+                            ("blog".to_string(), ServiceTarget::Directory(other_dir_key.clone())),
+                            ("icon".to_string(), favicon)];
+        for service in services.into_iter() {
+            let struct_data = unwrap_result!(dns_operations.add_service_target(&dns_name, service, &secret_signing_key, None));
+            unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        }
+
+        let references = unwrap_result!(dns_operations.find_services_referencing_directory(&old_dir_key, None));
+        assert_eq!(references, vec![(dns_name.clone(), "icon".to_string()), (dns_name.clone(), "www".to_string())]);
+
+        let struct_data_vec = unwrap_result!(dns_operations.retarget_directory(&old_dir_key, &new_dir_key, &secret_signing_key, None));
+        assert_eq!(struct_data_vec.len(), 1);
+        for struct_data in struct_data_vec.into_iter() {
+            unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        }
+
+        assert!(unwrap_result!(dns_operations.find_services_referencing_directory(&old_dir_key, None)).is_empty());
+        assert_eq!(unwrap_result!(dns_operations.get_service_home_directory_key(&dns_name, &"www".to_string(), None)), new_dir_key);
+        assert_eq!(unwrap_result!(dns_operations.get_service_home_directory_key(&dns_name, &"blog".to_string(), None)), other_dir_key);
+        assert_eq!(unwrap_result!(dns_operations.get_service_target(&dns_name, &"icon".to_string(), None)),
+                   ServiceTarget::File {
+                       directory: new_dir_key,
+                       file_name: "favicon.ico".to_string(),
+                   });
+    }
+}
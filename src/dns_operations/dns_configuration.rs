@@ -17,7 +17,6 @@
 
 use maidsafe_utilities::serialisation::{serialise, deserialise};
 
-const DNS_CONFIG_DIR_NAME: &'static str = "DnsReservedDirectory";
 const DNS_CONFIG_FILE_NAME: &'static str = "DnsConfigurationFile";
 
 #[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
@@ -28,46 +27,37 @@ pub struct DnsConfiguation {
 
 }
 
-pub fn initialise_dns_configuaration(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> Result<(), ::errors::DnsError> {
-    let dir_helper = ::safe_nfs::helper::directory_helper::DirectoryHelper::new(client.clone());
-    let dir_listing = try!(dir_helper.get_configuration_directory_listing(DNS_CONFIG_DIR_NAME.to_string()));
-    let file_helper = ::safe_nfs::helper::file_helper::FileHelper::new(client.clone());
-    match file_helper.create(DNS_CONFIG_FILE_NAME.to_string(), vec![], dir_listing) {
-        Ok(writer) => {
-            let _ = try!(writer.close());
-            Ok(())
-        },
-        Err(::safe_nfs::errors::NfsError::FileAlreadyExistsWithSameName) => Ok(()),
-        Err(error) => Err(::errors::DnsError::from(error)),
-    }
+pub fn initialise_dns_configuaration(storage  : &::dns_operations::Storage,
+                                     namespace: &::dns_operations::Namespace) -> Result<(), ::errors::DnsError> {
+    storage.initialise_configuration(&get_config_file_name(namespace))
 }
 
-pub fn get_dns_configuaration_data(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> Result<Vec<DnsConfiguation>, ::errors::DnsError> {
-    let dir_helper = ::safe_nfs::helper::directory_helper::DirectoryHelper::new(client.clone());
-    let dir_listing = try!(dir_helper.get_configuration_directory_listing(DNS_CONFIG_DIR_NAME.to_string()));
-    let file = try!(dir_listing.get_files().iter().find(|file| file.get_name() == DNS_CONFIG_FILE_NAME).ok_or(::errors::DnsError::DnsConfigFileNotFoundOrCorrupted));
-    let file_helper = ::safe_nfs::helper::file_helper::FileHelper::new(client.clone());
+pub fn get_dns_configuaration_data(storage  : &::dns_operations::Storage,
+                                   namespace: &::dns_operations::Namespace) -> Result<Vec<DnsConfiguation>, ::errors::DnsError> {
     debug!("Reading dns configuration data from file ...");
-    let mut reader = file_helper.read(file);
-    let size = reader.size();
-    if size != 0 {
-        Ok(try!(deserialise(&try!(reader.read(0, size)))))
+    let content = try!(storage.read_configuration(&get_config_file_name(namespace)));
+    if !content.is_empty() {
+        Ok(try!(deserialise(&content)))
     } else {
         Ok(vec![])
     }
 }
 
-pub fn write_dns_configuaration_data(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
-                                     config: &Vec<DnsConfiguation>) -> Result<(), ::errors::DnsError> {
-    let dir_helper = ::safe_nfs::helper::directory_helper::DirectoryHelper::new(client.clone());
-    let dir_listing = try!(dir_helper.get_configuration_directory_listing(DNS_CONFIG_DIR_NAME.to_string()));
-    let file = try!(dir_listing.get_files().iter().find(|file| file.get_name() == DNS_CONFIG_FILE_NAME).ok_or(::errors::DnsError::DnsConfigFileNotFoundOrCorrupted)).clone();
-    let file_helper = ::safe_nfs::helper::file_helper::FileHelper::new(client.clone());
-    let mut writer = try!(file_helper.update_content(file, ::safe_nfs::helper::writer::Mode::Overwrite, dir_listing));
+pub fn write_dns_configuaration_data(storage  : &::dns_operations::Storage,
+                                     namespace: &::dns_operations::Namespace,
+                                     config   : &Vec<DnsConfiguation>) -> Result<(), ::errors::DnsError> {
     debug!("Writing dns configuration data ...");
-    writer.write(&try!(serialise(&config)), 0);
-    let _ = try!(writer.close());
-    Ok(())
+    storage.write_configuration(&get_config_file_name(namespace), try!(serialise(&config)))
+}
+
+/// Names registered in other namespaces than the default one are kept in files of their own,
+/// told apart by both the name and the tag of the namespace
+fn get_config_file_name(namespace: &::dns_operations::Namespace) -> String {
+    if namespace.is_default() {
+        DNS_CONFIG_FILE_NAME.to_string()
+    } else {
+        format!("{}-{}-{}", DNS_CONFIG_FILE_NAME, namespace.get_name(), namespace.get_tag())
+    }
 }
 
 #[cfg(test)]
@@ -77,12 +67,14 @@ mod test {
     #[test]
     fn read_write_dns_configuration_file() {
         let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let storage = ::dns_operations::SafeCoreStorage::new(client);
+        let namespace = ::dns_operations::Namespace::default();
 
         // Initialise Dns Configuration File
-        unwrap_result!(initialise_dns_configuaration(client.clone()));
+        unwrap_result!(initialise_dns_configuaration(&storage, &namespace));
 
         // Get the Stored Configurations
-        let mut config_vec = unwrap_result!(get_dns_configuaration_data(client.clone()));
+        let mut config_vec = unwrap_result!(get_dns_configuaration_data(&storage, &namespace));
         assert_eq!(config_vec.len(), 0);
 
         let long_name = unwrap_result!(::safe_core::utility::generate_random_string(10));
@@ -95,10 +87,10 @@ mod test {
         };
 
         config_vec.push(config_0.clone());
-        unwrap_result!(write_dns_configuaration_data(client.clone(), &config_vec));
+        unwrap_result!(write_dns_configuaration_data(&storage, &namespace, &config_vec));
 
         // Get the Stored Configurations
-        config_vec = unwrap_result!(get_dns_configuaration_data(client.clone()));
+        config_vec = unwrap_result!(get_dns_configuaration_data(&storage, &namespace));
         assert_eq!(config_vec.len(), 1);
 
         assert_eq!(config_vec[0], config_0);
@@ -111,10 +103,10 @@ mod test {
         };
 
         config_vec[0] = config_1.clone();
-        unwrap_result!(write_dns_configuaration_data(client.clone(), &config_vec));
+        unwrap_result!(write_dns_configuaration_data(&storage, &namespace, &config_vec));
 
         // Get the Stored Configurations
-        config_vec = unwrap_result!(get_dns_configuaration_data(client.clone()));
+        config_vec = unwrap_result!(get_dns_configuaration_data(&storage, &namespace));
         assert_eq!(config_vec.len(), 1);
 
         assert!(config_vec[0] != config_0);
@@ -122,10 +114,10 @@ mod test {
 
         // Delete Record
         config_vec.clear();
-        unwrap_result!(write_dns_configuaration_data(client.clone(), &config_vec));
+        unwrap_result!(write_dns_configuaration_data(&storage, &namespace, &config_vec));
 
         // Get the Stored Configurations
-        config_vec = unwrap_result!(get_dns_configuaration_data(client.clone()));
+        config_vec = unwrap_result!(get_dns_configuaration_data(&storage, &namespace));
         assert_eq!(config_vec.len(), 0);
     }
 }
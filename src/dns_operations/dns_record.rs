@@ -0,0 +1,320 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use xor_name::XorName;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+
+/// Heads serialised Dns records, followed by `DNS_RECORD_FORMAT_VERSION` and the record. Records
+/// written before the envelope started with the Dns-name, which is not expected to contain `\0`.
+const DNS_RECORD_MARKER: &'static str = "\0safe_dns:record";
+/// Layout of `Dns` written after `DNS_RECORD_MARKER`
+const DNS_RECORD_FORMAT_VERSION: u64 = 1;
+
+/// Hands over the administration of a sub-label of a Dns-name (eg., `team-a` of `example.com`) to
+/// the owners of another structured data holding a Dns record.
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Delegation {
+    /// Identifier of the structured data housing the delegated Dns record
+    pub identifier: XorName,
+    /// Owners that the delegated structured data is expected to have. Resolution fails if the
+    /// structured data found at `identifier` is owned by anyone else.
+    pub owners    : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+}
+
+impl Delegation {
+    /// Delegate to the Dns record registered (eg., through `DnsOperations::register_dns`) under
+    /// the given fully qualified name, eg., team-a.example.com
+    pub fn to_registered_name(long_name: &String, owners: Vec<::sodiumoxide::crypto::sign::PublicKey>) -> Delegation {
+        Delegation::to_registered_name_in_namespace(long_name, owners, &super::Namespace::default())
+    }
+
+    /// Same as `to_registered_name`, for a Dns record registered in the given namespace
+    pub fn to_registered_name_in_namespace(long_name: &String,
+                                           owners   : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                                           namespace: &super::Namespace) -> Delegation {
+        Delegation {
+            identifier: namespace.get_identifier(long_name.as_bytes()),
+            owners    : owners,
+        }
+    }
+}
+
+/// What a service of a Dns-name points to
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum ServiceTarget {
+    /// Home directory of the service (eg., containing INDEX.html)
+    Directory(::safe_nfs::metadata::directory_key::DirectoryKey),
+    /// Permanent redirect to another SAFE url, eg., `safe:www.newname.com/blog`. This is not
+    /// followed by the library - browsers and gateways are expected to act on it.
+    Redirect(String),
+    /// A single file within a directory
+    File {
+        /// Directory containing the file
+        directory: ::safe_nfs::metadata::directory_key::DirectoryKey,
+        /// Name of the file within that directory
+        file_name: String,
+    },
+    /// Content-addressed blob stored as immutable data of the given name
+    ImmutableData(XorName),
+}
+
+/// What kind of service a service of a Dns-name is
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum ServiceKind {
+    /// Website meant to be displayed by a browser
+    Website,
+    /// Endpoint meant to be consumed by programs, eg., serving JSON
+    Api,
+    /// Messaging service
+    Messaging,
+    /// Any other kind, described by the given string
+    Other(String),
+}
+
+/// Metadata describing a service of a Dns-name
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct ServiceInfo {
+    /// Kind of the service
+    pub kind            : ServiceKind,
+    /// Human readable description
+    pub description     : String,
+    /// Protocol and/or version spoken by the service, eg., `json-api/2.1`
+    pub protocol_version: Option<String>,
+    /// Hints about the content served, eg., mime types like `text/html`
+    pub content_hints   : Vec<String>,
+}
+
+impl ServiceInfo {
+    /// Metadata with only the kind set
+    pub fn new(kind: ServiceKind) -> ServiceInfo {
+        ServiceInfo {
+            kind            : kind,
+            description     : String::new(),
+            protocol_version: None,
+            content_hints   : Vec::new(),
+        }
+    }
+}
+
+/// A service of a Dns-name, eg., `www`
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Service {
+    /// Mirrors serving the same content, primary first. Never empty.
+    pub targets: Vec<ServiceTarget>,
+    /// Metadata describing the service
+    pub info   : Option<ServiceInfo>,
+}
+
+impl Service {
+    /// Service with a single target and no metadata
+    pub fn new(target: ServiceTarget) -> Service {
+        Service {
+            targets: vec![target],
+            info   : None,
+        }
+    }
+
+    /// Target to be tried first
+    pub fn get_primary_target(&self) -> Result<&ServiceTarget, ::errors::DnsError> {
+        self.targets.first().ok_or(::errors::DnsError::ServiceTargetNotFound)
+    }
+}
+
+/// Reference to a service housed in its own structured data, so that it can be edited by owners
+/// other than those of the Dns record
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct ServiceLink {
+    /// Identifier of the structured data housing the service
+    pub identifier: XorName,
+    /// Owners that the structured data is expected to have. Lookups fail if the structured data
+    /// found at `identifier` is owned by anyone else.
+    pub owners    : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+}
+
+/// Proof that a Dns record was registered through a commitment, see
+/// `DnsOperations::reveal_dns_registration`
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct CommitmentProof {
+    /// Salt hiding the name in the commitment
+    pub salt: Vec<u8>,
+}
+
+/// Previous payload of a Dns record kept in the history
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct HistoryEntry {
+    /// Version of the housing structured data which carried this payload
+    pub version     : u64,
+    /// Name of the immutable data holding the payload
+    pub payload_name: XorName,
+}
+
+/// Bounded history of the previous payloads of a Dns record
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct History {
+    /// Maximum number of entries kept. Oldest entries are dropped first.
+    pub max_entries: u64,
+    /// Entries, oldest first
+    pub entries    : Vec<HistoryEntry>,
+}
+
+/// Dns record housed in the structured data of a Dns-name
+#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Dns {
+    /// Dns-name this record was registered under
+    pub long_name        : String,
+    /// Services (www, blog, micro-blog etc) of this Dns-name
+    pub services         : ::std::collections::HashMap<String, Service>,
+    /// Public key for encrypting messages to the owner of this Dns-name
+    pub encryption_key   : ::sodiumoxide::crypto::box_::PublicKey,
+    /// Sub-labels administered by owners of other Dns records
+    pub delegations      : ::std::collections::HashMap<String, Delegation>,
+    /// If set, this name is to be resolved as the given fully qualified name. Services and
+    /// delegations of an aliased record are retained but not used for resolution.
+    pub alias            : Option<String>,
+    /// Arbitrary key/value pairs, eg., verification tokens or contact info, like TXT records of
+    /// classic DNS
+    pub text_records     : ::std::collections::HashMap<String, String>,
+    /// Service used when a name is resolved without a service label, eg., `safe:example.com`
+    pub default_service  : Option<String>,
+    /// Service answering for every label not explicitly registered as a service, ie., `*`
+    pub wildcard_service : Option<Service>,
+    /// Previous payloads of this record, if keeping history is enabled
+    pub history          : Option<History>,
+    /// Immutable data chunks holding the services, if they had to be moved out for the record to
+    /// fit in a structured data. Records read through this library always have all the services
+    /// in `services` and this empty.
+    pub services_overflow: Vec<XorName>,
+    /// Services housed in their own structured data, see `DnsOperations::link_service`
+    pub service_links    : ::std::collections::HashMap<String, ServiceLink>,
+    /// Time (seconds since the UNIX epoch) after which this record can not be resolved unless
+    /// renewed, see `DnsOperations::renew_dns`. Never expires if unset.
+    pub expires_at       : Option<u64>,
+    /// Set if this record was registered through a commitment
+    pub commitment       : Option<CommitmentProof>,
+    /// Identifier of the structured data (of the same tag) this record was moved to by
+    /// `DnsOperations::migrate_dns`. Reads follow it; updates have to be made there.
+    pub forwarded_to     : Option<XorName>,
+}
+
+impl Dns {
+    /// True if the lease of this record has run out
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.map_or(false, |expires_at| expires_at < super::expiry::get_current_time())
+    }
+
+    /// Size of this record once serialised, ie., of what is stored in the housing structured
+    /// data before encryption. See `MAX_DNS_RECORD_SIZE`.
+    pub fn get_serialised_size(&self) -> Result<usize, ::errors::DnsError> {
+        Ok(try!(self.encode()).len())
+    }
+
+    /// Find the service for the given label. Explicit services win over the wildcard service; the
+    /// label is returned alongside if it was matched through the wildcard. Linked services are
+    /// not looked at - they need to be fetched (see `service_links`).
+    pub fn find_service(&self, service_name: &String) -> Result<(&Service, Option<String>), ::errors::DnsError> {
+        if let Some(service) = self.services.get(service_name) {
+            Ok((service, None))
+        } else if let Some(ref service) = self.wildcard_service {
+            Ok((service, Some(service_name.clone())))
+        } else {
+            Err(::errors::DnsError::ServiceNotFound)
+        }
+    }
+}
+
+impl Dns {
+    /// Serialise this record in its versioned envelope, as stored in the housing structured data
+    pub fn encode(&self) -> Result<Vec<u8>, ::errors::DnsError> {
+        Ok(try!(serialise(&(DNS_RECORD_MARKER.to_string(), DNS_RECORD_FORMAT_VERSION, self))))
+    }
+
+    /// Deserialise a record made by `encode`, or one stored in the layout used before records
+    /// were versioned (which only had the name, services and encryption key)
+    pub fn decode(content: &[u8]) -> Result<Dns, ::errors::DnsError> {
+        if let Ok((marker, version, dns_record)) = deserialise::<(String, u64, Dns)>(content) {
+            if marker == DNS_RECORD_MARKER {
+                return if version == DNS_RECORD_FORMAT_VERSION {
+                    Ok(dns_record)
+                } else {
+                    Err(::errors::DnsError::from("Unsupported dns record format version"))
+                }
+            }
+        }
+
+        let legacy_dns_record: LegacyDns = try!(deserialise(content));
+        Ok(Dns {
+            long_name        : legacy_dns_record.long_name,
+            services         : legacy_dns_record.services
+                                                .into_iter()
+                                                .map(|(service_name, dir_key)| (service_name, Service::new(ServiceTarget::Directory(dir_key))))
+                                                .collect(),
+            encryption_key   : legacy_dns_record.encryption_key,
+            delegations      : ::std::collections::HashMap::new(),
+            alias            : None,
+            text_records     : ::std::collections::HashMap::new(),
+            default_service  : None,
+            wildcard_service : None,
+            history          : None,
+            services_overflow: Vec::new(),
+            service_links    : ::std::collections::HashMap::new(),
+            expires_at       : None,
+            commitment       : None,
+            forwarded_to     : None,
+        })
+    }
+}
+
+/// Layout of Dns records written before records were versioned
+#[derive(RustcEncodable, RustcDecodable)]
+struct LegacyDns {
+    long_name     : String,
+    services      : ::std::collections::HashMap<String, ::safe_nfs::metadata::directory_key::DirectoryKey>,
+    encryption_key: ::sodiumoxide::crypto::box_::PublicKey,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use xor_name::XorName;
+    use maidsafe_utilities::serialisation::serialise;
+
+    #[test]
+    fn decode_legacy_dns_record() {
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+        let mut services = ::std::collections::HashMap::new();
+        let _ = services.insert("www".to_string(), home_dir_key.clone());
+        let legacy_dns_record = LegacyDns {
+            long_name     : "example.com".to_string(),
+            services      : services,
+            encryption_key: ::sodiumoxide::crypto::box_::gen_keypair().0,
+        };
+
+        let dns_record = unwrap_result!(Dns::decode(&unwrap_result!(serialise(&legacy_dns_record))));
+        assert_eq!(dns_record.long_name, legacy_dns_record.long_name);
+        assert_eq!(dns_record.encryption_key, legacy_dns_record.encryption_key);
+        assert_eq!(dns_record.services.len(), 1);
+        assert_eq!(unwrap_result!(unwrap_option!(dns_record.services.get("www"), "Service missing").get_primary_target()),
+                   &ServiceTarget::Directory(home_dir_key));
+        assert!(dns_record.delegations.is_empty());
+
+        // Decoding what was encoded gives the record back
+        assert_eq!(unwrap_result!(Dns::decode(&unwrap_result!(dns_record.encode()))), dns_record);
+    }
+}
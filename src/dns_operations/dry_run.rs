@@ -0,0 +1,226 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use xor_name::XorName;
+use super::{DataEncryptionKeys, DnsOperations};
+use super::dns_record::Dns;
+use super::dns_configuration;
+
+/// Outcome of a mutating operation run on a dry-run instance (see `DnsOperations::dry_run`)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Plan {
+    /// Dns record the operation would have published, `None` if it would have deleted it
+    pub dns_record      : Option<Dns>,
+    /// Names that would be registered in the dns configuration of the user afterwards
+    pub registered_names: Vec<String>,
+}
+
+/// State of a dry-run instance
+pub struct DryRun {
+    configs: Option<Vec<dns_configuration::DnsConfiguation>>,
+    plan   : Option<Plan>,
+}
+
+impl DnsOperations {
+    /// Get an instance sharing the storage of this one which performs all reads and checks of the
+    /// mutating operations, but writes neither to the dns configuration nor to the storage.
+    /// The outcome of each operation is available through `take_plan`. The structured data
+    /// returned by the operations is unsigned, carries the Dns record unencrypted and is only
+    /// meant to be inspected - the network rejects it.
+    /// Changes to the dns configuration are remembered by the dry-run instance, so eg., a
+    /// registration can be planned followed by the removal of a service; Dns records are always
+    /// read from the storage though.
+    pub fn dry_run(&self) -> DnsOperations {
+        DnsOperations {
+            storage           : self.storage.clone(),
+            dry_run           : Some(::std::sync::Mutex::new(DryRun {
+                configs: None,
+                plan   : None,
+            })),
+            min_commitment_age: self.min_commitment_age,
+            owner_index       : self.owner_index,
+            name_directory    : self.name_directory.clone(),
+            namespace         : self.namespace.clone(),
+        }
+    }
+
+    /// Get the outcome of the last mutating operation run on this dry-run instance. `None` if
+    /// this is not a dry-run instance or no operation was run since the last call.
+    pub fn take_plan(&self) -> Option<Plan> {
+        self.dry_run.as_ref().and_then(|dry_run| unwrap_result!(dry_run.lock()).plan.take())
+    }
+}
+
+/// Dns configuration of the user, as changed so far if this is a dry-run instance
+pub fn get_dns_configuration(dns_operations: &DnsOperations) -> Result<Vec<dns_configuration::DnsConfiguation>, ::errors::DnsError> {
+    if let Some(ref dry_run) = dns_operations.dry_run {
+        if let Some(ref configs) = unwrap_result!(dry_run.lock()).configs {
+            return Ok(configs.clone())
+        }
+    }
+    dns_configuration::get_dns_configuaration_data(&*dns_operations.storage, &dns_operations.namespace)
+}
+
+/// Replace the dns configuration of the user - only remembered if this is a dry-run instance
+pub fn write_dns_configuration(dns_operations: &DnsOperations,
+                               configs       : &Vec<dns_configuration::DnsConfiguation>) -> Result<(), ::errors::DnsError> {
+    match dns_operations.dry_run {
+        Some(ref dry_run) => {
+            debug!("Dry run - not writing dns configuration data ...");
+            let mut dry_run = unwrap_result!(dry_run.lock());
+            if let Some(ref mut plan) = dry_run.plan {
+                plan.registered_names = configs.iter().map(|config| config.long_name.clone()).collect();
+            }
+            dry_run.configs = Some(configs.clone());
+            Ok(())
+        },
+        None => dns_configuration::write_dns_configuaration_data(&*dns_operations.storage, &dns_operations.namespace, configs),
+    }
+}
+
+/// Structured data carrying the given Dns record (or nothing, when deleting). Services are
+/// spilled if needed, reusing the chunks in `prev_services_overflow` (see
+/// `overflow::spill_services`). For a dry-run instance the outcome is recorded and an unsigned,
+/// unencrypted structured data returned.
+pub fn create_structured_data(dns_operations        : &DnsOperations,
+                              identifier            : XorName,
+                              version               : u64,
+                              dns_record            : Option<&Dns>,
+                              prev_services_overflow: &Vec<XorName>,
+                              owners                : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              prev_owners           : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              private_signing_key   : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_keys  : DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+    let data = match dns_record {
+        Some(dns_record) => try!(try!(super::overflow::spill_services(dns_operations,
+                                                                      dns_record,
+                                                                      prev_services_overflow,
+                                                                      data_encryption_keys)).encode()),
+        None => vec![],
+    };
+    try!(super::validation::validate_dns_record_size(data.len()));
+
+    if let Some(ref dry_run) = dns_operations.dry_run {
+        let registered_names = try!(get_dns_configuration(dns_operations)).iter().map(|config| config.long_name.clone()).collect();
+        debug!("Dry run - recording plan instead of creating structured data ...");
+        unwrap_result!(dry_run.lock()).plan = Some(Plan {
+            dns_record      : dns_record.cloned(),
+            registered_names: registered_names,
+        });
+    }
+
+    create_tagged_structured_data(dns_operations,
+                                  dns_operations.namespace.get_tag(),
+                                  identifier,
+                                  version,
+                                  data,
+                                  owners,
+                                  prev_owners,
+                                  private_signing_key,
+                                  data_encryption_keys)
+}
+
+/// Structured data of the given tag carrying the given data. For a dry-run instance an
+/// unsigned, unencrypted structured data is returned.
+pub fn create_tagged_structured_data(dns_operations      : &DnsOperations,
+                                     tag                 : u64,
+                                     identifier          : XorName,
+                                     version             : u64,
+                                     data                : Vec<u8>,
+                                     owners              : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                                     prev_owners         : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                                     private_signing_key : &::sodiumoxide::crypto::sign::SecretKey,
+                                     data_encryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+    match dns_operations.dry_run {
+        Some(_) => StructuredData::new(tag, identifier, version, data, owners, prev_owners, None)
+            .map_err(|error| ::errors::DnsError::from(::safe_core::errors::CoreError::from(error))),
+        None => dns_operations.storage.create_structured_data(tag,
+                                                              identifier,
+                                                              version,
+                                                              data,
+                                                              owners,
+                                                              prev_owners,
+                                                              private_signing_key,
+                                                              data_encryption_keys),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use xor_name::XorName;
+    use dns_operations::DnsOperations;
+    use dns_operations::test_utils;
+
+    #[test]
+    fn dry_run_writes_nothing() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+        let dry_run = dns_operations.dry_run();
+        assert!(dns_operations.take_plan().is_none());
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+        let services = vec![("www".to_string(), home_dir_key.clone())];
+
+        // Planned registration leaves the configuration untouched
+        let _ = unwrap_result!(dry_run.register_dns(dns_name.clone(),
+                                                    &messaging_keypair.0,
+                                                    &messaging_keypair.1,
+                                                    &services,
+                                                    owners.clone(),
+                                                    &secret_signing_key,
+                                                    None));
+        let plan = unwrap_option!(dry_run.take_plan(), "");
+        assert!(plan.registered_names.contains(&dns_name));
+        assert!(unwrap_option!(plan.dns_record, "").services.contains_key("www"));
+        assert!(dry_run.take_plan().is_none());
+        assert!(!unwrap_result!(dns_operations.get_all_registered_names()).contains(&dns_name));
+
+        // Real registration
+        let (struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                        &messaging_keypair.0,
+                                                                        &messaging_keypair.1,
+                                                                        &services,
+                                                                        owners,
+                                                                        &secret_signing_key,
+                                                                        None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        // Checks are still performed
+        let dry_run = dns_operations.dry_run();
+        match dry_run.remove_service(&dns_name, "blog".to_string(), &secret_signing_key, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::ServiceNotFound) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+        assert!(dry_run.take_plan().is_none());
+
+        let _ = unwrap_result!(dry_run.remove_service(&dns_name, "www".to_string(), &secret_signing_key, None));
+        let plan = unwrap_option!(dry_run.take_plan(), "");
+        assert_eq!(unwrap_option!(plan.dns_record, "").services.len(), 0);
+        assert_eq!(unwrap_result!(dns_operations.get_all_services(&dns_name, None)), vec!["www".to_string()]);
+    }
+}
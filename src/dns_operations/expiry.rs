@@ -0,0 +1,136 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use super::{DataEncryptionKeys, DnsOperations};
+
+/// Longest lease (in seconds) a single renewal can extend the expiry of a Dns record by
+pub const MAX_LEASE_DURATION: u64 = 5 * 365 * 24 * 60 * 60;
+
+impl DnsOperations {
+    /// Extend the lease of the given Dns-name by `lease_duration` seconds, counted from its
+    /// current expiry or from now, whichever is later. Names never renewed do not expire; once
+    /// renewed they expire unless renewed again. Expired names can not be resolved but can still
+    /// be renewed by their owners.
+    pub fn renew_dns(&self,
+                     long_name                      : &String,
+                     lease_duration                 : u64,
+                     private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                     data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        if lease_duration == 0 || lease_duration > MAX_LEASE_DURATION {
+            return Err(::errors::DnsError::InvalidLeaseDuration)
+        }
+
+        let now = get_current_time();
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            let lease_start = dns_record.expires_at.map_or(now, |expires_at| ::std::cmp::max(expires_at, now));
+            debug!("Renewing lease till {:?} ...", lease_start + lease_duration);
+            dns_record.expires_at = Some(lease_start + lease_duration);
+            Ok(())
+        })
+    }
+
+    /// Get the Dns-names registered by this user which expire within the given number of seconds
+    /// (or have expired already), along with their expiry time in seconds since the UNIX epoch,
+    /// soonest first.
+    pub fn get_names_nearing_expiry(&self,
+                                    within              : u64,
+                                    data_decryption_keys: DataEncryptionKeys) -> Result<Vec<(String, u64)>, ::errors::DnsError> {
+        let deadline = get_current_time().saturating_add(within);
+        let mut expiring_names = Vec::new();
+        for config in try!(super::dry_run::get_dns_configuration(self)).into_iter() {
+            let (_, dns_record) = try!(self.get_housing_structured_data_and_dns_record(&config.long_name, data_decryption_keys));
+            if let Some(expires_at) = dns_record.expires_at {
+                if expires_at <= deadline {
+                    expiring_names.push((config.long_name, expires_at));
+                }
+            }
+        }
+
+        expiring_names.sort_by(|a, b| a.1.cmp(&b.1));
+        Ok(expiring_names)
+    }
+}
+
+/// Seconds since the UNIX epoch
+pub fn get_current_time() -> u64 {
+    match ::std::time::SystemTime::now().duration_since(::std::time::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use dns_operations::DnsOperations;
+    use dns_operations::test_utils;
+
+    #[test]
+    fn renew_and_report_expiring_names() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let (dns_name, keys) = test_utils::register_test_name(&dns_operations);
+        let secret_signing_key = keys.secret_signing_key;
+        assert!(unwrap_result!(dns_operations.get_names_nearing_expiry(super::MAX_LEASE_DURATION, None)).is_empty());
+
+        match dns_operations.renew_dns(&dns_name, 0, &secret_signing_key, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::InvalidLeaseDuration) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+
+        let lease_duration = 30 * 24 * 60 * 60;
+        let mut struct_data = unwrap_result!(dns_operations.renew_dns(&dns_name, lease_duration, &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        assert!(unwrap_result!(dns_operations.get_names_nearing_expiry(60, None)).is_empty());
+        let expiring_names = unwrap_result!(dns_operations.get_names_nearing_expiry(2 * lease_duration, None));
+        assert_eq!(expiring_names.len(), 1);
+        assert_eq!(expiring_names[0].0, dns_name);
+
+        // Renewal extends from the current expiry
+        let first_expiry = expiring_names[0].1;
+        struct_data = unwrap_result!(dns_operations.renew_dns(&dns_name, lease_duration, &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        let expiring_names = unwrap_result!(dns_operations.get_names_nearing_expiry(3 * lease_duration, None));
+        assert_eq!(expiring_names[0].1, first_expiry + lease_duration);
+
+        // Expired names can not be read, only renewed
+        struct_data = unwrap_result!(dns_operations.update_dns_record(&dns_name, &secret_signing_key, None, |dns_record| {
+            dns_record.expires_at = Some(1);
+            Ok(())
+        }));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        match dns_operations.get_all_services(&dns_name, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::DnsRecordExpired) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+        match dns_operations.resolve_name(&format!("www.{}", dns_name), None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::DnsRecordExpired) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+
+        struct_data = unwrap_result!(dns_operations.renew_dns(&dns_name, lease_duration, &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert!(unwrap_result!(dns_operations.get_all_services(&dns_name, None)).is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use super::{DataEncryptionKeys, DnsOperations};
+use super::dns_record::{Dns, History, HistoryEntry};
+
+/// Maximum number of previous payloads a Dns record can be asked to keep
+pub const MAX_HISTORY_ENTRIES: u64 = 64;
+
+impl DnsOperations {
+    /// Start keeping (or with `None` stop keeping and forget) the previous payloads of the given
+    /// Dns-name. Each later change stores the payload it replaces as immutable data, keeping at
+    /// most `max_entries` of them.
+    pub fn set_history_limit(&self,
+                             long_name                      : &String,
+                             max_entries                    : Option<u64>,
+                             private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                             data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        if let Some(max_entries) = max_entries {
+            if max_entries == 0 || max_entries > MAX_HISTORY_ENTRIES {
+                return Err(::errors::DnsError::InvalidHistoryLimit)
+            }
+        }
+
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            debug!("Setting history limit to {:?} ...", max_entries);
+            dns_record.history = max_entries.map(|max_entries| {
+                let mut history = dns_record.history.take().unwrap_or(History {
+                    max_entries: max_entries,
+                    entries    : Vec::new(),
+                });
+                history.max_entries = max_entries;
+                history
+            });
+            Ok(())
+        })
+    }
+
+    /// Get the versions of the previous payloads kept for the given Dns-name, oldest first.
+    pub fn get_history(&self,
+                       long_name           : &String,
+                       data_decryption_keys: DataEncryptionKeys) -> Result<Vec<u64>, ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        Ok(dns_record.history.map_or(Vec::new(), |history| history.entries.iter().map(|entry| entry.version).collect()))
+    }
+
+    /// Get the Dns record as it was at the given version. Only versions listed by `get_history`
+    /// are available.
+    pub fn get_historic_dns_record(&self,
+                                   long_name           : &String,
+                                   version             : u64,
+                                   data_decryption_keys: DataEncryptionKeys) -> Result<Dns, ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        let entry = try!(dns_record.history
+                                   .as_ref()
+                                   .and_then(|history| history.entries.iter().find(|entry| entry.version == version))
+                                   .cloned()
+                                   .ok_or(::errors::DnsError::HistoryVersionNotFound));
+
+        debug!("Retrieving version {:?} of {:?} dns from history ...", version, long_name);
+        let payload = try!(self.get_decrypted_immutable_data(entry.payload_name, data_decryption_keys));
+        let (stored_version, encoded_dns_record): (u64, Vec<u8>) = try!(deserialise(&payload));
+        if stored_version != version {
+            return Err(::errors::DnsError::from("Historic dns record does not carry the expected version"))
+        }
+        let mut historic_dns_record = try!(Dns::decode(&encoded_dns_record));
+        try!(super::overflow::load_spilled_services(self, &mut historic_dns_record, data_decryption_keys));
+        Ok(historic_dns_record)
+    }
+
+    /// Restore the given Dns-name to how it was at the given version, eg., to undo a bad deploy.
+    /// History is kept as it is - the payload being replaced is added to it as usual, so a
+    /// rollback can itself be rolled back.
+    pub fn rollback_to(&self,
+                       long_name                      : &String,
+                       version                        : u64,
+                       private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                       data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        let historic_dns_record = try!(self.get_historic_dns_record(long_name, version, data_encryption_decryption_keys));
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            debug!("Rolling back to version {:?} ...", version);
+            let history = dns_record.history.take();
+            *dns_record = historic_dns_record;
+            dns_record.history = history;
+            Ok(())
+        })
+    }
+}
+
+/// If `dns_record` keeps history, store `prev_dns_record` (the payload it replaces, as carried by
+/// the structured data, so overflowed services are kept in the chunks they already are in) as
+/// immutable data and add it to the history, dropping the oldest entries beyond the limit.
+pub fn record_history(dns_operations      : &DnsOperations,
+                      prev_version        : u64,
+                      mut prev_dns_record : Dns,
+                      dns_record          : &mut Dns,
+                      data_encryption_keys: DataEncryptionKeys) -> Result<(), ::errors::DnsError> {
+    let history = match dns_record.history {
+        Some(ref mut history) => history,
+        None => return Ok(()),
+    };
+
+    prev_dns_record.history = None;
+    let payload = try!(serialise(&(prev_version, try!(prev_dns_record.encode()))));
+
+    debug!("Storing version {:?} in history ...", prev_version);
+    let payload_name = try!(dns_operations.put_encrypted_immutable_data(payload, data_encryption_keys));
+    history.entries.push(HistoryEntry {
+        version     : prev_version,
+        payload_name: payload_name,
+    });
+    while history.entries.len() as u64 > history.max_entries {
+        let _ = history.entries.remove(0);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use xor_name::XorName;
+    use dns_operations::DnsOperations;
+    use dns_operations::test_utils;
+
+    #[test]
+    fn keep_history_and_rollback() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+
+        let (mut struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            &vec![("www".to_string(), home_dir_key.clone())],
+                                                                            owners,
+                                                                            &secret_signing_key,
+                                                                            None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        // Enabling history records the payload it replaces (version 0)
+        struct_data = unwrap_result!(dns_operations.set_history_limit(&dns_name, Some(2), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations.get_history(&dns_name, None)), vec![0]);
+
+        // A bad deploy (version 1 -> 2)
+        struct_data = unwrap_result!(dns_operations.remove_service(&dns_name, "www".to_string(), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations.get_history(&dns_name, None)), vec![0, 1]);
+        assert_eq!(unwrap_result!(dns_operations.get_all_services(&dns_name, None)).len(), 0);
+
+        let historic_dns_record = unwrap_result!(dns_operations.get_historic_dns_record(&dns_name, 1, None));
+        assert!(historic_dns_record.services.contains_key("www"));
+
+        // Rollback - the oldest entry is dropped as only 2 are kept
+        struct_data = unwrap_result!(dns_operations.rollback_to(&dns_name, 1, &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations.get_history(&dns_name, None)), vec![1, 2]);
+        assert_eq!(unwrap_result!(dns_operations.get_service_home_directory_key(&dns_name, &"www".to_string(), None)),
+                   home_dir_key);
+
+        match dns_operations.get_historic_dns_record(&dns_name, 0, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::HistoryVersionNotFound) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use xor_name::XorName;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use super::{DataEncryptionKeys, DnsOperations, validation};
+use super::dns_record::{Dns, Service, ServiceLink};
+
+/// Tag of the structured data housing a service linked from a Dns record
+pub const LINKED_SERVICE_TAG: u64 = 6;
+
+impl DnsOperations {
+    /// Hand over the editing of the given service of a Dns-name to the given owners. The service
+    /// is then housed in its own structured data (see `create_linked_service`), which the owners
+    /// of the Dns-name can not modify, but only unlink. Linked services are stored unencrypted as
+    /// their owners need not hold the keys of the Dns record.
+    pub fn link_service(&self,
+                        long_name                      : &String,
+                        service_name                   : String,
+                        owners                         : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                        private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                        data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validation::validate_service_name(&service_name));
+        let link = ServiceLink {
+            identifier: get_linked_service_identifier(&self.namespace, long_name, &service_name, &owners),
+            owners    : owners,
+        };
+
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            if dns_record.services.contains_key(&service_name) ||
+               dns_record.service_links.contains_key(&service_name) ||
+               dns_record.delegations.contains_key(&service_name) {
+                return Err(::errors::DnsError::ServiceAlreadyExists)
+            }
+            debug!("Linking service ...");
+            let _ = dns_record.service_links.insert(service_name, link);
+            Ok(())
+        })
+    }
+
+    /// Stop using the separately housed service of a Dns-name. The structured data housing it is
+    /// left to its owners, but is not used again should the service be linked to other owners.
+    pub fn unlink_service(&self,
+                          long_name                      : &String,
+                          service_name                   : &String,
+                          private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                          data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            debug!("Unlinking service ...");
+            let _ = try!(dns_record.service_links.remove(service_name).ok_or(::errors::DnsError::ServiceNotFound));
+            if dns_record.default_service.as_ref() == Some(service_name) {
+                dns_record.default_service = None;
+            }
+            Ok(())
+        })
+    }
+
+    /// Create the structured data housing a linked service. This is meant to be called by the
+    /// owners of the service (eg., a contractor), who need not have registered the Dns-name.
+    /// `owners` must be those the service was linked to.
+    pub fn create_linked_service(&self,
+                                 long_name          : &String,
+                                 service_name       : &String,
+                                 service            : Service,
+                                 owners             : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                                 private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validation::validate_service_name(service_name));
+        try!(validate_service(&service));
+
+        debug!("Creating linked service {:?} of {:?} dns ...", service_name, long_name);
+        super::dry_run::create_tagged_structured_data(self,
+                                                      LINKED_SERVICE_TAG,
+                                                      get_linked_service_identifier(&self.namespace, long_name, service_name, &owners),
+                                                      0,
+                                                      try!(serialise(&service)),
+                                                      owners,
+                                                      vec![],
+                                                      private_signing_key,
+                                                      None)
+    }
+
+    /// Replace a linked service. This is meant to be called by the owners of the service, passing
+    /// the owners it was created with.
+    pub fn update_linked_service(&self,
+                                 long_name          : &String,
+                                 service_name       : &String,
+                                 service            : Service,
+                                 owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                                 private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validate_service(&service));
+
+        let identifier = get_linked_service_identifier(&self.namespace, long_name, service_name, owners);
+        let prev_struct_data = try!(self.get_tagged_structured_data(identifier, LINKED_SERVICE_TAG));
+        debug!("Updating linked service {:?} of {:?} dns ...", service_name, long_name);
+        super::dry_run::create_tagged_structured_data(self,
+                                                      LINKED_SERVICE_TAG,
+                                                      prev_struct_data.get_identifier().clone(),
+                                                      prev_struct_data.get_version() + 1,
+                                                      try!(serialise(&service)),
+                                                      prev_struct_data.get_owner_keys().clone(),
+                                                      prev_struct_data.get_previous_owner_keys().clone(),
+                                                      private_signing_key,
+                                                      None)
+    }
+}
+
+/// Identifier of the structured data housing the given linked service of a Dns-name. The owners
+/// are part of it, so that linking the service to other owners houses it elsewhere and those it
+/// was linked to before can not take it over again.
+fn get_linked_service_identifier(namespace   : &super::Namespace,
+                                 long_name   : &String,
+                                 service_name: &String,
+                                 owners      : &Vec<::sodiumoxide::crypto::sign::PublicKey>) -> XorName {
+    let mut seed = format!("{}.{}", service_name, long_name).into_bytes();
+    for owner in owners.iter() {
+        seed.extend(owner.0.iter());
+    }
+    namespace.get_identifier(&seed)
+}
+
+fn validate_service(service: &Service) -> Result<(), ::errors::DnsError> {
+    if service.targets.is_empty() {
+        return Err(::errors::DnsError::ServiceTargetNotFound)
+    }
+    for target in service.targets.iter() {
+        try!(validation::validate_service_target(target));
+    }
+    if let Some(ref info) = service.info {
+        try!(validation::validate_service_info(info));
+    }
+    Ok(())
+}
+
+/// Find the service for the given label, fetching it if it is linked. Linked services win over
+/// the wildcard service as explicit ones do.
+pub fn find_service(dns_operations: &DnsOperations,
+                    dns_record    : &Dns,
+                    service_name  : &String) -> Result<(Service, Option<String>), ::errors::DnsError> {
+    match dns_record.service_links.get(service_name) {
+        Some(link) => Ok((try!(get_linked_service(dns_operations, link)), None)),
+        None => dns_record.find_service(service_name).map(|(service, wildcard_label)| (service.clone(), wildcard_label)),
+    }
+}
+
+fn get_linked_service(dns_operations: &DnsOperations, link: &ServiceLink) -> Result<Service, ::errors::DnsError> {
+    let struct_data = try!(dns_operations.get_tagged_structured_data(link.identifier.clone(), LINKED_SERVICE_TAG));
+    if *struct_data.get_owner_keys() != link.owners {
+        return Err(::errors::DnsError::ServiceOwnerMismatch)
+    }
+    Ok(try!(deserialise(&try!(dns_operations.storage.get_structured_data_content(&struct_data, None)))))
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use xor_name::XorName;
+    use dns_operations::{DnsOperations, Service, ServiceInfo, ServiceKind, ServiceTarget};
+    use dns_operations::test_utils;
+
+    #[test]
+    fn manage_linked_services() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let (dns_name, keys) = test_utils::register_test_name(&dns_operations);
+        let secret_signing_key = keys.secret_signing_key;
+        let contractor_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+
+        // Name owner links the service, contractor creates and updates it
+        let blog = "blog".to_string();
+        let mut struct_data = unwrap_result!(dns_operations.link_service(&dns_name, blog.clone(), vec![contractor_keypair.0], &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let first_target = ServiceTarget::ImmutableData(XorName::new([1; 64]));
+        struct_data = unwrap_result!(dns_operations.create_linked_service(&dns_name,
+                                                                        &blog,
+                                                                        Service::new(first_target.clone()),
+                                                                        vec![contractor_keypair.0],
+                                                                        &contractor_keypair.1));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations.get_service_target(&dns_name, &blog, None)), first_target);
+        assert!(unwrap_result!(dns_operations.get_all_services(&dns_name, None)).contains(&blog));
+
+        let second_target = ServiceTarget::ImmutableData(XorName::new([2; 64]));
+        struct_data = unwrap_result!(dns_operations.update_linked_service(&dns_name,
+                                                                        &blog,
+                                                                        Service::new(second_target.clone()),
+                                                                        &vec![contractor_keypair.0],
+                                                                        &contractor_keypair.1));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let resolution = unwrap_result!(dns_operations.resolve_name(&format!("blog.{}", dns_name), None));
+        assert_eq!(unwrap_result!(dns_operations.get_resolved_service_target(&resolution, &blog, None)), second_target);
+
+        // Linked services can not be shadowed by the name owner
+        match dns_operations.add_service_target(&dns_name, (blog.clone(), first_target.clone()), &secret_signing_key, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::ServiceAlreadyExists) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+
+        struct_data = unwrap_result!(dns_operations.unlink_service(&dns_name, &blog, &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert!(!unwrap_result!(dns_operations.get_all_services(&dns_name, None)).contains(&blog));
+
+        // Linked to someone else, the service is housed elsewhere - the previous contractor's
+        // structured data is not used again
+        let new_contractor_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+        struct_data = unwrap_result!(dns_operations.link_service(&dns_name, blog.clone(), vec![new_contractor_keypair.0], &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert!(dns_operations.get_service_target(&dns_name, &blog, None).is_err());
+
+        struct_data = unwrap_result!(dns_operations.create_linked_service(&dns_name,
+                                                                        &blog,
+                                                                        Service::new(first_target.clone()),
+                                                                        vec![new_contractor_keypair.0],
+                                                                        &new_contractor_keypair.1));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations.get_service_target(&dns_name, &blog, None)), first_target);
+    }
+
+    #[test]
+    fn linked_services_are_not_edited_through_dns_record() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let (dns_name, keys) = test_utils::register_test_name(&dns_operations);
+        let secret_signing_key = keys.secret_signing_key;
+        let contractor_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+
+        let blog = "blog".to_string();
+        let mut struct_data = unwrap_result!(dns_operations.link_service(&dns_name, blog.clone(), vec![contractor_keypair.0], &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let target = ServiceTarget::ImmutableData(XorName::new([1; 64]));
+        let mirror = ServiceTarget::ImmutableData(XorName::new([2; 64]));
+        struct_data = unwrap_result!(dns_operations.create_linked_service(&dns_name,
+                                                                        &blog,
+                                                                        Service::new(target.clone()),
+                                                                        vec![contractor_keypair.0],
+                                                                        &contractor_keypair.1));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        // Only the owners of the linked service can edit it
+        let results = vec![dns_operations.add_service_mirror(&dns_name, &blog, mirror.clone(), &secret_signing_key, None),
+                           dns_operations.remove_service_mirror(&dns_name, &blog, &target, &secret_signing_key, None),
+                           dns_operations.set_service_info(&dns_name, &blog, Some(ServiceInfo::new(ServiceKind::Website)), &secret_signing_key, None)];
+        for result in results.into_iter() {
+            match result {
+                Ok(_) => panic!("Should have been an error"),
+                Err(::errors::DnsError::ServiceIsLinked) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+
+        // Removing the service unlinks it
+        struct_data = unwrap_result!(dns_operations.remove_service(&dns_name, blog.clone(), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert!(!unwrap_result!(dns_operations.get_all_services(&dns_name, None)).contains(&blog));
+    }
+}
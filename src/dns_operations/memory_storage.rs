@@ -0,0 +1,233 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use xor_name::XorName;
+use routing::{ImmutableData, StructuredData};
+use super::Storage;
+
+/// Storage kept in memory, eg., for testing the Dns logic without a network. Posted and deleted
+/// structured data must be the next version of the stored one, signed by its owners, as on the
+/// network. No NFS directories are held, so services pointing to directories can not be fetched.
+pub struct MemoryStorage {
+    structured_data: Mutex<HashMap<(XorName, u64), StructuredData>>,
+    immutable_data : Mutex<HashMap<XorName, ImmutableData>>,
+    configuration  : Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    /// Empty storage
+    pub fn new() -> MemoryStorage {
+        MemoryStorage {
+            structured_data: Mutex::new(HashMap::new()),
+            immutable_data : Mutex::new(HashMap::new()),
+            configuration  : Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> MemoryStorage {
+        MemoryStorage::new()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn get_structured_data(&self, identifier: XorName, tag: u64) -> Result<StructuredData, ::errors::DnsError> {
+        unwrap_result!(self.structured_data.lock()).get(&(identifier, tag)).cloned().ok_or(::errors::DnsError::DataNotFound)
+    }
+
+    fn put_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError> {
+        let mut structured_data = unwrap_result!(self.structured_data.lock());
+        let key = (struct_data.get_identifier().clone(), struct_data.get_type_tag());
+        if structured_data.contains_key(&key) {
+            return Err(::errors::DnsError::from("Structured data already exists"))
+        }
+        let _ = structured_data.insert(key, struct_data);
+        Ok(())
+    }
+
+    fn post_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError> {
+        let mut structured_data = unwrap_result!(self.structured_data.lock());
+        let key = (struct_data.get_identifier().clone(), struct_data.get_type_tag());
+        let prev_struct_data = try!(structured_data.get_mut(&key).ok_or(::errors::DnsError::DataNotFound));
+        // Checks the version and the signatures of the owners of the stored version
+        prev_struct_data.replace_with_other(struct_data).map_err(|error| ::errors::DnsError::from(::safe_core::errors::CoreError::from(error)))
+    }
+
+    fn delete_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError> {
+        let mut structured_data = unwrap_result!(self.structured_data.lock());
+        let key = (struct_data.get_identifier().clone(), struct_data.get_type_tag());
+        let mut prev_struct_data = try!(structured_data.get(&key).cloned().ok_or(::errors::DnsError::DataNotFound));
+        try!(prev_struct_data.replace_with_other(struct_data).map_err(|error| ::errors::DnsError::from(::safe_core::errors::CoreError::from(error))));
+        let _ = structured_data.remove(&key);
+        Ok(())
+    }
+
+    fn create_structured_data(&self,
+                              tag                 : u64,
+                              identifier          : XorName,
+                              version             : u64,
+                              data                : Vec<u8>,
+                              owners              : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              prev_owners         : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              private_signing_key : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
+                                                            &::sodiumoxide::crypto::box_::SecretKey,
+                                                            &::sodiumoxide::crypto::box_::Nonce)>) -> Result<StructuredData, ::errors::DnsError> {
+        let data = match data_encryption_keys {
+            Some((public_key, secret_key, nonce)) => ::sodiumoxide::crypto::box_::seal(&data, nonce, public_key, secret_key),
+            None => data,
+        };
+        StructuredData::new(tag, identifier, version, data, owners, prev_owners, Some(private_signing_key))
+            .map_err(|error| ::errors::DnsError::from(::safe_core::errors::CoreError::from(error)))
+    }
+
+    fn get_structured_data_content(&self,
+                                   struct_data         : &StructuredData,
+                                   data_decryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
+                                                                 &::sodiumoxide::crypto::box_::SecretKey,
+                                                                 &::sodiumoxide::crypto::box_::Nonce)>) -> Result<Vec<u8>, ::errors::DnsError> {
+        match data_decryption_keys {
+            Some((public_key, secret_key, nonce)) => ::sodiumoxide::crypto::box_::open(struct_data.get_data(), nonce, public_key, secret_key)
+                .map_err(|_| ::errors::DnsError::from("Could not decrypt structured data")),
+            None => Ok(struct_data.get_data().clone()),
+        }
+    }
+
+    fn get_immutable_data(&self, name: XorName) -> Result<ImmutableData, ::errors::DnsError> {
+        unwrap_result!(self.immutable_data.lock()).get(&name).cloned().ok_or(::errors::DnsError::DataNotFound)
+    }
+
+    fn put_immutable_data(&self, immutable_data: ImmutableData) -> Result<(), ::errors::DnsError> {
+        // Immutable data is content addressed, so storing it again changes nothing
+        let _ = unwrap_result!(self.immutable_data.lock()).insert(immutable_data.name(), immutable_data);
+        Ok(())
+    }
+
+    fn get_directory_listing(&self,
+                             _directory_key: &::safe_nfs::metadata::directory_key::DirectoryKey) -> Result<::safe_nfs::directory_listing::DirectoryListing,
+                                                                                                         ::errors::DnsError> {
+        Err(::errors::DnsError::DataNotFound)
+    }
+
+    fn initialise_configuration(&self, file_name: &String) -> Result<(), ::errors::DnsError> {
+        let _ = unwrap_result!(self.configuration.lock()).entry(file_name.clone()).or_insert_with(Vec::new);
+        Ok(())
+    }
+
+    fn read_configuration(&self, file_name: &String) -> Result<Vec<u8>, ::errors::DnsError> {
+        unwrap_result!(self.configuration.lock()).get(file_name).cloned().ok_or(::errors::DnsError::DnsConfigFileNotFoundOrCorrupted)
+    }
+
+    fn write_configuration(&self, file_name: &String, content: Vec<u8>) -> Result<(), ::errors::DnsError> {
+        let mut configuration = unwrap_result!(self.configuration.lock());
+        let file = try!(configuration.get_mut(file_name).ok_or(::errors::DnsError::DnsConfigFileNotFoundOrCorrupted));
+        *file = content;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryStorage;
+    use xor_name::XorName;
+    use dns_operations::{DnsOperations, Namespace, ServiceTarget, Storage};
+    use dns_operations::storage::is_data_not_found;
+
+    #[test]
+    fn run_dns_operations_in_memory() {
+        let storage = ::std::sync::Arc::new(MemoryStorage::new());
+        let dns_operations = unwrap_result!(DnsOperations::new_with_storage(storage.clone(), Namespace::default()));
+
+        let dns_name = "example.com".to_string();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let signing_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+        let data_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let nonce = ::sodiumoxide::crypto::box_::gen_nonce();
+        let data_keys = Some((&data_keypair.0, &data_keypair.1, &nonce));
+
+        let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                                             &messaging_keypair.0,
+                                                                                             &messaging_keypair.1,
+                                                                                             &vec![],
+                                                                                             vec![signing_keypair.0],
+                                                                                             &signing_keypair.1,
+                                                                                             data_keys));
+        assert!(auxiliary_struct_data.is_empty());
+        unwrap_result!(storage.put_structured_data(struct_data));
+        assert_eq!(unwrap_result!(dns_operations.get_all_registered_names()), vec![dns_name.clone()]);
+
+        let target = ServiceTarget::ImmutableData(XorName::new([1; 64]));
+        let struct_data = unwrap_result!(dns_operations.add_service_target(&dns_name,
+                                                                         ("www".to_string(), target.clone()),
+                                                                         &signing_keypair.1,
+                                                                         data_keys));
+        unwrap_result!(storage.post_structured_data(struct_data));
+        assert_eq!(unwrap_result!(dns_operations.get_service_target(&dns_name, &"www".to_string(), data_keys)), target);
+
+        let (struct_data, _) = unwrap_result!(dns_operations.delete_dns(&dns_name, &signing_keypair.1));
+        unwrap_result!(storage.delete_structured_data(struct_data));
+        assert!(unwrap_result!(dns_operations.get_all_registered_names()).is_empty());
+        match dns_operations.get_service_target(&dns_name, &"www".to_string(), data_keys) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(ref error) if is_data_not_found(error) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+
+    #[test]
+    fn reject_posts_not_signed_by_owners() {
+        let storage = ::std::sync::Arc::new(MemoryStorage::new());
+        let signing_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+        let other_signing_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+        let identifier = XorName::new([2; 64]);
+
+        let struct_data = unwrap_result!(storage.create_structured_data(15000,
+                                                                        identifier.clone(),
+                                                                        0,
+                                                                        vec![1],
+                                                                        vec![signing_keypair.0],
+                                                                        vec![],
+                                                                        &signing_keypair.1,
+                                                                        None));
+        unwrap_result!(storage.put_structured_data(struct_data));
+
+        let struct_data = unwrap_result!(storage.create_structured_data(15000,
+                                                                        identifier.clone(),
+                                                                        1,
+                                                                        vec![2],
+                                                                        vec![other_signing_keypair.0],
+                                                                        vec![],
+                                                                        &other_signing_keypair.1,
+                                                                        None));
+        assert!(storage.post_structured_data(struct_data.clone()).is_err());
+        assert!(storage.delete_structured_data(struct_data).is_err());
+
+        let struct_data = unwrap_result!(storage.create_structured_data(15000,
+                                                                        identifier.clone(),
+                                                                        1,
+                                                                        vec![2],
+                                                                        vec![signing_keypair.0],
+                                                                        vec![],
+                                                                        &signing_keypair.1,
+                                                                        None));
+        unwrap_result!(storage.post_structured_data(struct_data));
+        assert_eq!(*unwrap_result!(storage.get_structured_data(identifier, 15000)).get_data(), vec![2]);
+    }
+}
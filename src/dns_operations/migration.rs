@@ -0,0 +1,256 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+use routing::StructuredData;
+use super::{DataEncryptionKeys, DnsOperations, Namespace};
+use super::dns_record::Dns;
+use super::dns_configuration::DnsConfiguation;
+
+/// Outcome of migrating a name, see `DnsOperations::migrate_all`
+pub type MigrationOutcome = Result<(StructuredData, Vec<StructuredData>), ::errors::DnsError>;
+
+impl DnsOperations {
+    /// Move the Dns record of a name the user registered in `previous_namespace` to the
+    /// identifier derived in this instance's namespace, eg., after switching to another
+    /// `IdentifierScheme`. The name is looked up in the dns configuration of the previous
+    /// namespace (`Namespace::default()` for names registered before namespaces existed) and
+    /// added to that of this one. Both namespaces must house their Dns records under the same tag.
+    /// Returned along with the structured data republishing the record, to be put, is the
+    /// auxiliary structured data to be updated afterwards (see `register_dns`), first of which
+    /// the forwarding left at the previous identifier. Instances still deriving the previous
+    /// identifier follow the forwarding when reading, but fail to update the record with
+    /// `DnsRecordMoved`. Names registered through commitments can not be migrated.
+    pub fn migrate_dns(&self,
+                       long_name                      : &String,
+                       previous_namespace             : &Namespace,
+                       private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                       data_encryption_decryption_keys: DataEncryptionKeys) -> MigrationOutcome {
+        let previous_configs = try!(super::dns_configuration::get_dns_configuaration_data(&*self.storage, previous_namespace));
+        let config = try!(previous_configs.into_iter()
+                                          .find(|config| config.long_name == *long_name)
+                                          .ok_or(::errors::DnsError::DnsRecordNotFound));
+        self.migrate_dns_impl(config, previous_namespace, private_signing_key, data_encryption_decryption_keys)
+    }
+
+    /// Migrate every name the user registered in `previous_namespace`, see `migrate_dns`. The
+    /// outcome is reported for each name, so that a name failing to migrate does not hold back
+    /// the others.
+    pub fn migrate_all(&self,
+                       previous_namespace             : &Namespace,
+                       private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                       data_encryption_decryption_keys: DataEncryptionKeys) -> Result<Vec<(String, MigrationOutcome)>, ::errors::DnsError> {
+        let previous_configs = try!(super::dns_configuration::get_dns_configuaration_data(&*self.storage, previous_namespace));
+        Ok(previous_configs.into_iter().map(|config| {
+            let long_name = config.long_name.clone();
+            (long_name, self.migrate_dns_impl(config, previous_namespace, private_signing_key, data_encryption_decryption_keys))
+        }).collect())
+    }
+
+    /// Migrate the name of the given entry of the dns configuration of `previous_namespace`
+    fn migrate_dns_impl(&self,
+                        config                         : DnsConfiguation,
+                        previous_namespace             : &Namespace,
+                        private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                        data_encryption_decryption_keys: DataEncryptionKeys) -> MigrationOutcome {
+        let long_name = config.long_name.clone();
+        if self.min_commitment_age.is_some() {
+            return Err(::errors::DnsError::InvalidCommitment)
+        }
+        if previous_namespace.get_tag() != self.namespace.get_tag() {
+            return Err(::errors::DnsError::from("Namespaces house Dns records under different tags"))
+        }
+        let previous_identifier = previous_namespace.get_identifier(long_name.as_bytes());
+        let identifier = self.get_identifier(&long_name);
+        if previous_identifier == identifier {
+            return Err(::errors::DnsError::from("Namespaces derive the same identifier"))
+        }
+
+        let prev_struct_data = match self.get_structured_data(previous_identifier) {
+            Ok(struct_data) => struct_data,
+            Err(ref error) if super::storage::is_data_not_found(error) => return Err(::errors::DnsError::DnsRecordNotFound),
+            Err(error) => return Err(error),
+        };
+        let spilled_dns_record = try!(self.read_spilled_dns_record(&prev_struct_data, data_encryption_decryption_keys));
+        if spilled_dns_record.forwarded_to.is_some() {
+            return Err(::errors::DnsError::DnsRecordMoved)
+        }
+        let prev_services_overflow = spilled_dns_record.services_overflow.clone();
+        let mut dns_record = spilled_dns_record;
+        try!(super::overflow::load_spilled_services(self, &mut dns_record, data_encryption_decryption_keys));
+
+        debug!("Republishing {:?} dns under new identifier ...", long_name);
+        let owners = prev_struct_data.get_owner_keys().clone();
+        let struct_data = try!(super::dry_run::create_structured_data(self,
+                                                                      identifier.clone(),
+                                                                      0,
+                                                                      Some(&dns_record),
+                                                                      &prev_services_overflow,
+                                                                      owners.clone(),
+                                                                      vec![],
+                                                                      private_signing_key,
+                                                                      data_encryption_decryption_keys));
+
+        // Only the forwarding is looked at, so nothing else is duplicated
+        debug!("Leaving forwarding at previous identifier ...");
+        let forwarding_dns_record = Dns {
+            long_name        : dns_record.long_name,
+            services         : ::std::collections::HashMap::new(),
+            encryption_key   : dns_record.encryption_key,
+            delegations      : ::std::collections::HashMap::new(),
+            alias            : None,
+            text_records     : ::std::collections::HashMap::new(),
+            default_service  : None,
+            wildcard_service : None,
+            history          : None,
+            services_overflow: Vec::new(),
+            service_links    : ::std::collections::HashMap::new(),
+            expires_at       : None,
+            commitment       : None,
+            forwarded_to     : Some(identifier),
+        };
+        let mut auxiliary_struct_data = vec![try!(super::dry_run::create_tagged_structured_data(self,
+                                                                                                prev_struct_data.get_type_tag(),
+                                                                                                prev_struct_data.get_identifier().clone(),
+                                                                                                prev_struct_data.get_version() + 1,
+                                                                                                try!(forwarding_dns_record.encode()),
+                                                                                                owners.clone(),
+                                                                                                prev_struct_data.get_previous_owner_keys().clone(),
+                                                                                                private_signing_key,
+                                                                                                data_encryption_decryption_keys))];
+        auxiliary_struct_data.extend(try!(super::owner_index::index_signed_name(self, &long_name, &owners, private_signing_key)));
+        auxiliary_struct_data.extend(try!(super::name_directory::list_name(self, &long_name, &owners, private_signing_key)));
+
+        let mut saved_configs = try!(super::dry_run::get_dns_configuration(self));
+        if !saved_configs.iter().any(|saved_config| saved_config.long_name == long_name) {
+            debug!("Adding encryption key pair to saved dns configuration ...");
+            saved_configs.push(config);
+            try!(super::dry_run::write_dns_configuration(self, &saved_configs));
+        }
+
+        Ok((struct_data, auxiliary_struct_data))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use routing::Data;
+    use xor_name::XorName;
+    use dns_operations::{DnsOperations, DomainSeparatedIdentifierScheme, Namespace};
+    use dns_operations::test_utils;
+
+    #[test]
+    fn migrate_to_domain_separated_identifiers() {
+        let client = Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let legacy_dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+        let (dns_name, keys) = test_utils::register_test_name(&legacy_dns_operations);
+        let secret_signing_key = keys.secret_signing_key;
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([1; 64]),
+                                                                                  15000,
+                                                                                  false,
+                                                                                  ::safe_nfs::AccessLevel::Public);
+        let struct_data = unwrap_result!(legacy_dns_operations.add_service(&dns_name,
+                                                                           ("www".to_string(), home_dir_key.clone()),
+                                                                           &secret_signing_key,
+                                                                           None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let namespace = Namespace::default().with_identifier_scheme(Arc::new(DomainSeparatedIdentifierScheme));
+        let dns_operations = unwrap_result!(DnsOperations::new_in_namespace(client.clone(), namespace));
+        assert!(dns_operations.get_service_home_directory_key(&dns_name, &"www".to_string(), None).is_err());
+
+        let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.migrate_dns(&dns_name,
+                                                                                            &Namespace::default(),
+                                                                                            &secret_signing_key,
+                                                                                            None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+
+        // Both identifiers resolve to the migrated record
+        assert_eq!(unwrap_result!(dns_operations.get_service_home_directory_key(&dns_name, &"www".to_string(), None)),
+                   home_dir_key);
+        assert_eq!(unwrap_result!(legacy_dns_operations.get_service_home_directory_key(&dns_name, &"www".to_string(), None)),
+                   home_dir_key);
+
+        // Updates have to be made through the new identifier
+        let blog_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([2; 64]),
+                                                                                  15000,
+                                                                                  false,
+                                                                                  ::safe_nfs::AccessLevel::Public);
+        match legacy_dns_operations.add_service(&dns_name, ("blog".to_string(), blog_dir_key.clone()), &secret_signing_key, None) {
+            Ok(_) => panic!("Moved record should not be updated"),
+            Err(::errors::DnsError::DnsRecordMoved) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+        let struct_data = unwrap_result!(dns_operations.add_service(&dns_name,
+                                                                    ("blog".to_string(), blog_dir_key.clone()),
+                                                                    &secret_signing_key,
+                                                                    None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(legacy_dns_operations.get_service_home_directory_key(&dns_name, &"blog".to_string(), None)),
+                   blog_dir_key);
+
+        // Migrating again finds the record already moved
+        match dns_operations.migrate_dns(&dns_name, &Namespace::default(), &secret_signing_key, None) {
+            Ok(_) => panic!("Record should not be migrated twice"),
+            Err(::errors::DnsError::DnsRecordMoved) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+
+    #[test]
+    fn migrate_all_reports_failures_per_name() {
+        let client = Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let legacy_dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+        let (first_name, keys) = test_utils::register_test_name(&legacy_dns_operations);
+        let (second_name, _) = test_utils::register_test_name(&legacy_dns_operations);
+
+        // Saved to the dns configuration, but never stored
+        let unstored_name = test_utils::generate_random_dns_name();
+        let _ = unwrap_result!(legacy_dns_operations.register_dns(unstored_name.clone(),
+                                                                  &keys.messaging_keypair.0,
+                                                                  &keys.messaging_keypair.1,
+                                                                  &vec![],
+                                                                  keys.owners.clone(),
+                                                                  &keys.secret_signing_key,
+                                                                  None));
+
+        let namespace = Namespace::default().with_identifier_scheme(Arc::new(DomainSeparatedIdentifierScheme));
+        let dns_operations = unwrap_result!(DnsOperations::new_in_namespace(client.clone(), namespace));
+        let outcomes = unwrap_result!(dns_operations.migrate_all(&Namespace::default(), &keys.secret_signing_key, None));
+        assert_eq!(outcomes.len(), 3);
+
+        for (long_name, outcome) in outcomes.into_iter() {
+            if long_name == unstored_name {
+                match outcome {
+                    Ok(_) => panic!("Unstored record should not be migrated"),
+                    Err(::errors::DnsError::DnsRecordNotFound) => (),
+                    Err(error) => panic!("{:?}", error),
+                }
+            } else {
+                assert!(long_name == first_name || long_name == second_name);
+                let (struct_data, auxiliary_struct_data) = unwrap_result!(outcome);
+                unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+                test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+            }
+        }
+
+        for long_name in vec![first_name, second_name].iter() {
+            assert!(unwrap_result!(dns_operations.get_all_services(long_name, None)).is_empty());
+        }
+    }
+}
@@ -16,26 +16,99 @@
 // relating to use of the SAFE Network Software.
 
 use xor_name::XorName;
-use routing::{Data, DataRequest, StructuredData};
-use maidsafe_utilities::serialisation::{serialise, deserialise};
+use routing::{ImmutableData, ImmutableDataType, StructuredData};
 
+mod commitment;
+mod diff;
+mod directory_references;
+mod dry_run;
+mod expiry;
+mod history;
+mod linked_service;
+mod memory_storage;
+mod migration;
+mod name_directory;
+mod namespace;
+mod overflow;
+mod owner_index;
+mod resolver;
+mod storage;
+mod dns_record;
+mod validation;
 mod dns_configuration;
+#[cfg(test)]
+mod test_utils;
+
+pub use self::commitment::{MIN_COMMITMENT_AGE, COMMITMENT_SALT_LENGTH, MAX_COMMITMENT_CLAIMS};
+pub use self::dns_record::{CommitmentProof, Delegation, Dns, History, HistoryEntry, Service, ServiceInfo, ServiceKind, ServiceLink,
+                           ServiceTarget};
+pub use self::diff::{Change, DnsDiff, ServiceDiff, StructuredDataDiff};
+pub use self::directory_references::WILDCARD_SERVICE_LABEL;
+pub use self::dry_run::Plan;
+pub use self::expiry::MAX_LEASE_DURATION;
+pub use self::history::MAX_HISTORY_ENTRIES;
+pub use self::name_directory::{NameFilter, NamePage, MAX_DIRECTORY_PAGE_SIZE, MAX_DIRECTORY_SEARCH_READS, MAX_LISTED_NAME_LENGTH};
+pub use self::memory_storage::MemoryStorage;
+pub use self::migration::MigrationOutcome;
+pub use self::namespace::{IdentifierScheme, LegacyIdentifierScheme, DomainSeparatedIdentifierScheme, Namespace};
+pub use self::overflow::{MAX_SERVICE_CHUNK_SIZE, MAX_SERVICE_CHUNKS};
+pub use self::owner_index::MAX_OWNER_INDEX_SIZE;
+pub use self::storage::{Storage, SafeCoreStorage};
+pub use self::resolver::{MirrorSelection, Resolution, ServiceHandle, MIN_REGISTERED_NAME_LABELS, MAX_DELEGATION_DEPTH, MAX_ALIAS_DEPTH};
+pub use self::validation::{validate_service_name, RESERVED_SERVICE_NAMES, MAX_SERVICE_NAME_LENGTH, MAX_NAME_LABELS,
+                           MAX_TEXT_RECORD_KEY_LENGTH, MAX_TEXT_RECORD_VALUE_LENGTH, MAX_TEXT_RECORDS,
+                           MAX_SERVICE_DESCRIPTION_LENGTH, MAX_SERVICE_INFO_FIELD_LENGTH, MAX_SERVICE_CONTENT_HINTS,
+                           MAX_DNS_RECORD_SIZE};
 
+/// Tag of the structured data housing Dns records in the default namespace
 const DNS_TAG: u64 = 5;
 
+/// Service a name resolves to when neither the name nor its Dns record specify one
+pub const DEFAULT_SERVICE_NAME: &'static str = "www";
+
+/// Keys the Dns record and the data it refers to are encrypted with, if any
+pub type DataEncryptionKeys<'a> = Option<(&'a ::sodiumoxide::crypto::box_::PublicKey,
+                                          &'a ::sodiumoxide::crypto::box_::SecretKey,
+                                          &'a ::sodiumoxide::crypto::box_::Nonce)>;
+
 /// This is a representational structure for all safe-dns operations
 pub struct DnsOperations {
-    client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    storage           : ::std::sync::Arc<Storage>,
+    dry_run           : Option<::std::sync::Mutex<dry_run::DryRun>>,
+    min_commitment_age: Option<u64>,
+    owner_index       : bool,
+    name_directory    : Option<String>,
+    namespace         : Namespace,
 }
 
 impl DnsOperations {
     /// Create a new instance of DnsOperations. It is intended that only one of this be created as
     /// it operates on global data such as files.
     pub fn new(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> Result<DnsOperations, ::errors::DnsError> {
-        try!(dns_configuration::initialise_dns_configuaration(client.clone()));
+        DnsOperations::new_in_namespace(client, Namespace::default())
+    }
+
+    /// Same as `new`, operating on the Dns-names of the given namespace. The names registered by
+    /// the user are kept track of separately for each namespace.
+    pub fn new_in_namespace(client   : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                            namespace: Namespace) -> Result<DnsOperations, ::errors::DnsError> {
+        DnsOperations::new_with_storage(::std::sync::Arc::new(SafeCoreStorage::new(client)), namespace)
+    }
+
+    /// Same as `new_in_namespace`, keeping the Dns records and the dns configuration in the given
+    /// storage instead of on the network through a safe_core client, eg., a `MemoryStorage` to
+    /// exercise the Dns logic without a network.
+    pub fn new_with_storage(storage  : ::std::sync::Arc<Storage>,
+                            namespace: Namespace) -> Result<DnsOperations, ::errors::DnsError> {
+        try!(dns_configuration::initialise_dns_configuaration(&*storage, &namespace));
 
         Ok(DnsOperations {
-            client: client,
+            storage           : storage,
+            dry_run           : None,
+            min_commitment_age: None,
+            owner_index       : false,
+            name_directory    : None,
+            namespace         : namespace,
         })
     }
 
@@ -45,12 +118,39 @@ impl DnsOperations {
     /// It is intended that only one of this be created as it operates on global data such as
     /// files.
     pub fn new_unregistered(unregistered_client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> DnsOperations {
+        DnsOperations::new_unregistered_in_namespace(unregistered_client, Namespace::default())
+    }
+
+    /// Same as `new_unregistered`, resolving the Dns-names of the given namespace.
+    pub fn new_unregistered_in_namespace(unregistered_client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                                         namespace          : Namespace) -> DnsOperations {
         DnsOperations {
-            client: unregistered_client,
+            storage           : ::std::sync::Arc::new(SafeCoreStorage::new(unregistered_client)),
+            dry_run           : None,
+            min_commitment_age: None,
+            owner_index       : false,
+            name_directory    : None,
+            namespace         : namespace,
         }
     }
 
+    /// Storage the Dns records and the dns configuration of this instance are kept in
+    pub fn get_storage(&self) -> &::std::sync::Arc<Storage> {
+        &self.storage
+    }
+
+    /// Namespace the Dns-names of this instance live in
+    pub fn get_namespace(&self) -> &Namespace {
+        &self.namespace
+    }
+
     /// Register one's own Dns - eg., pepsico.com, spandansharma.com, krishnakumar.in etc
+    /// Instances requiring commitments (see `with_required_commitments`) only register through
+    /// `reveal_dns_registration`.
+    /// Along with the structured data housing the Dns record, the auxiliary structured data to be
+    /// updated is returned, eg., the index of the owner (see `with_owner_index`) or the slot of
+    /// the name directory (see `with_name_directory`). It is to be put to the network after the
+    /// record - or posted, unless its version is 0.
     pub fn register_dns(&self,
                         long_name                      : String,
                         public_messaging_encryption_key: &::sodiumoxide::crypto::box_::PublicKey,
@@ -60,19 +160,77 @@ impl DnsOperations {
                         private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
                         data_encryption_keys           : Option<(&::sodiumoxide::crypto::box_::PublicKey,
                                                                  &::sodiumoxide::crypto::box_::SecretKey,
-                                                                 &::sodiumoxide::crypto::box_::Nonce)>) -> Result<StructuredData, ::errors::DnsError> {
+                                                                 &::sodiumoxide::crypto::box_::Nonce)>) -> Result<(StructuredData,
+                                                                                                                   Vec<StructuredData>), ::errors::DnsError> {
+        if self.min_commitment_age.is_some() {
+            return Err(::errors::DnsError::InvalidCommitment)
+        }
+
+        let identifier = self.get_identifier(&long_name);
+        self.register_dns_impl(long_name,
+                               identifier,
+                               public_messaging_encryption_key,
+                               secret_messaging_encryption_key,
+                               services,
+                               owners,
+                               None,
+                               private_signing_key,
+                               data_encryption_keys)
+    }
+
+    /// Register the Dns record under the given identifier
+    fn register_dns_impl(&self,
+                         long_name                      : String,
+                         identifier                     : XorName,
+                         public_messaging_encryption_key: &::sodiumoxide::crypto::box_::PublicKey,
+                         secret_messaging_encryption_key: &::sodiumoxide::crypto::box_::SecretKey,
+                         services                       : &Vec<(String, ::safe_nfs::metadata::directory_key::DirectoryKey)>,
+                         owners                         : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                         commitment                     : Option<CommitmentProof>,
+                         private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                         data_encryption_keys           : DataEncryptionKeys) -> Result<(StructuredData, Vec<StructuredData>), ::errors::DnsError> {
         debug!("Registering {:?} dns ...", long_name);
-        let mut saved_configs = try!(dns_configuration::get_dns_configuaration_data(self.client.clone()));
+        if try!(validation::split_name_labels(&long_name)).len() < MIN_REGISTERED_NAME_LABELS {
+            return Err(::errors::DnsError::InvalidDnsName)
+        }
+        for &(ref service_name, _) in services.iter() {
+            try!(validation::validate_service_name(service_name));
+        }
+
+        let mut saved_configs = try!(dry_run::get_dns_configuration(self));
         if saved_configs.iter().any(|config| config.long_name == long_name) {
             Err(::errors::DnsError::DnsNameAlreadyRegistered)
         } else {
-            let identifier = XorName::new(::sodiumoxide::crypto::hash::sha512::hash(long_name.as_bytes()).0);
-
             let dns_record = Dns {
-                long_name     : long_name.clone(),
-                services      : services.iter().map(|a| a.clone()).collect(),
-                encryption_key: public_messaging_encryption_key.clone(),
+                long_name        : long_name.clone(),
+                services         : services.iter().map(|a| (a.0.clone(), Service::new(ServiceTarget::Directory(a.1.clone())))).collect(),
+                encryption_key   : public_messaging_encryption_key.clone(),
+                delegations      : ::std::collections::HashMap::new(),
+                alias            : None,
+                text_records     : ::std::collections::HashMap::new(),
+                default_service  : None,
+                wildcard_service : None,
+                history          : None,
+                services_overflow: Vec::new(),
+                service_links    : ::std::collections::HashMap::new(),
+                expires_at       : None,
+                commitment       : commitment,
+                forwarded_to     : None,
             };
+            let struct_data = try!(dry_run::create_structured_data(self,
+                                                                   identifier,
+                                                                   0,
+                                                                   Some(&dns_record),
+                                                                   &vec![],
+                                                                   owners.clone(),
+                                                                   vec![],
+                                                                   private_signing_key,
+                                                                   data_encryption_keys));
+            let mut auxiliary_struct_data: Vec<StructuredData> = try!(owner_index::index_signed_name(self,
+                                                                                                     &long_name,
+                                                                                                     &owners,
+                                                                                                     private_signing_key)).into_iter().collect();
+            auxiliary_struct_data.extend(try!(name_directory::list_name(self, &long_name, &owners, private_signing_key)));
 
             debug!("Adding encryption key pair to saved dns configuration ...");
             saved_configs.push(dns_configuration::DnsConfiguation {
@@ -81,47 +239,96 @@ impl DnsOperations {
                                      secret_messaging_encryption_key.clone())
 
             });
-            try!(dns_configuration::write_dns_configuaration_data(self.client.clone(), &saved_configs));
-
-            Ok(try!(::safe_core::structured_data_operations::unversioned::create(self.client.clone(),
-                                                                                   DNS_TAG,
-                                                                                   identifier,
-                                                                                   0,
-                                                                                   try!(serialise(&dns_record)),
-                                                                                   owners,
-                                                                                   vec![],
-                                                                                   private_signing_key,
-                                                                                   data_encryption_keys)))
+            try!(dry_run::write_dns_configuration(self, &saved_configs));
+
+            Ok((struct_data, auxiliary_struct_data))
         }
     }
 
-    /// Delete the Dns-Record
+    /// Delete the Dns-Record. Returned along with the structured data to be deleted is the
+    /// auxiliary structured data to be updated afterwards, see `register_dns`.
     pub fn delete_dns(&self,
                       long_name          : &String,
-                      private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<StructuredData, ::errors::DnsError> {
-        let mut saved_configs = try!(dns_configuration::get_dns_configuaration_data(self.client.clone()));
+                      private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<(StructuredData, Vec<StructuredData>), ::errors::DnsError> {
+        let mut saved_configs = try!(dry_run::get_dns_configuration(self));
         let pos = try!(saved_configs.iter().position(|config| config.long_name == *long_name).ok_or(::errors::DnsError::DnsRecordNotFound));
 
         let prev_struct_data = try!(self.get_housing_structured_data(long_name));
+        let struct_data = try!(dry_run::create_structured_data(self,
+                                                               prev_struct_data.get_identifier().clone(),
+                                                               prev_struct_data.get_version() + 1,
+                                                               None,
+                                                               &vec![],
+                                                               prev_struct_data.get_owner_keys().clone(),
+                                                               prev_struct_data.get_previous_owner_keys().clone(),
+                                                               private_signing_key,
+                                                               None));
+        let mut auxiliary_struct_data: Vec<StructuredData> = try!(owner_index::unindex_signed_name(self,
+                                                                                                 long_name,
+                                                                                                 prev_struct_data.get_owner_keys(),
+                                                                                                 private_signing_key)).into_iter().collect();
+        auxiliary_struct_data.extend(try!(name_directory::unlist_name(self, long_name, prev_struct_data.get_owner_keys(), private_signing_key)));
 
         debug!("Removing dns saved configs at {:?} position ...", pos);
         let _ = saved_configs.remove(pos);
-        try!(dns_configuration::write_dns_configuaration_data(self.client.clone(), &saved_configs));
+        try!(dry_run::write_dns_configuration(self, &saved_configs));
 
-        Ok(try!(::safe_core::structured_data_operations::unversioned::create(self.client.clone(),
-                                                                               DNS_TAG,
-                                                                               prev_struct_data.get_identifier().clone(),
-                                                                               prev_struct_data.get_version() + 1,
-                                                                               vec![],
-                                                                               prev_struct_data.get_owner_keys().clone(),
-                                                                               prev_struct_data.get_previous_owner_keys().clone(),
-                                                                               private_signing_key,
-                                                                               None)))
+        Ok((struct_data, auxiliary_struct_data))
+    }
+
+    /// Hand the Dns-Record over to the given owners. Unless the owner signing is among them, the
+    /// name is dropped from the names registered by the user and from the index of the owner
+    /// signing, if kept. The slot listing the name in the name directory, if any, is handed over
+    /// too. Returned along with the structured data carrying the record is the auxiliary
+    /// structured data to be updated afterwards, see `register_dns`. The new owners add the name
+    /// to their own indexes through `index_owned_name`.
+    pub fn transfer_dns(&self,
+                        long_name                      : &String,
+                        new_owners                     : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                        private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                        data_encryption_decryption_keys: DataEncryptionKeys) -> Result<(StructuredData, Vec<StructuredData>), ::errors::DnsError> {
+        let mut saved_configs = try!(dry_run::get_dns_configuration(self));
+        let pos = try!(saved_configs.iter().position(|config| config.long_name == *long_name).ok_or(::errors::DnsError::DnsRecordNotFound));
+
+        let prev_struct_data = try!(self.get_housing_structured_data(long_name));
+        let dns_record = try!(self.read_spilled_dns_record(&prev_struct_data, data_encryption_decryption_keys));
+        if dns_record.forwarded_to.is_some() {
+            return Err(::errors::DnsError::DnsRecordMoved)
+        }
+
+        debug!("Transferring {:?} dns to new owners ...", long_name);
+        let struct_data = try!(dry_run::create_structured_data(self,
+                                                               prev_struct_data.get_identifier().clone(),
+                                                               prev_struct_data.get_version() + 1,
+                                                               Some(&dns_record),
+                                                               &dns_record.services_overflow,
+                                                               new_owners.clone(),
+                                                               prev_struct_data.get_owner_keys().clone(),
+                                                               private_signing_key,
+                                                               data_encryption_decryption_keys));
+
+        let mut auxiliary_struct_data: Vec<StructuredData> = try!(name_directory::transfer_listed_name(self,
+                                                                                                   long_name,
+                                                                                                   prev_struct_data.get_owner_keys(),
+                                                                                                   &new_owners,
+                                                                                                   private_signing_key)).into_iter().collect();
+        if owner_index::get_signing_owner(&new_owners, private_signing_key).is_none() {
+            auxiliary_struct_data.extend(try!(owner_index::unindex_signed_name(self,
+                                                                               long_name,
+                                                                               prev_struct_data.get_owner_keys(),
+                                                                               private_signing_key)));
+
+            debug!("Removing dns saved configs at {:?} position ...", pos);
+            let _ = saved_configs.remove(pos);
+            try!(dry_run::write_dns_configuration(self, &saved_configs));
+        }
+
+        Ok((struct_data, auxiliary_struct_data))
     }
 
     /// Get all the Dns-names registered by the user so far in the network.
     pub fn get_all_registered_names(&self) -> Result<Vec<String>, ::errors::DnsError> {
-        dns_configuration::get_dns_configuaration_data(self.client.clone()).map(|v| v.iter().map(|a| a.long_name.clone()).collect())
+        dry_run::get_dns_configuration(self).map(|v| v.iter().map(|a| a.long_name.clone()).collect())
     }
 
     /// Get the messaging encryption keys that the user has associated with one's particular Dns-name.
@@ -138,35 +345,62 @@ impl DnsOperations {
                             data_decryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
                                                           &::sodiumoxide::crypto::box_::SecretKey,
                                                           &::sodiumoxide::crypto::box_::Nonce)>) -> Result<Vec<String>, ::errors::DnsError> {
-        // Allow unregistered clients to access this function
-        match self.find_dns_record(long_name) {
-            Ok(_) => (),
-            Err(::errors::DnsError::CoreError(::safe_core::errors::CoreError::OperationForbiddenForClient)) => (),
-            Err(::errors::DnsError::NfsError(::safe_nfs::errors::NfsError::CoreError(::safe_core::errors::CoreError::OperationForbiddenForClient))) => (),
-            Err(error) => return Err(error),
-        };
-
-        let (_, dns_record) = try!(self.get_housing_structured_data_and_dns_record(long_name, data_decryption_keys));
-        Ok(dns_record.services.keys().map(|a| a.clone()).collect())
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        Ok(dns_record.services.keys().chain(dns_record.service_links.keys()).map(|a| a.clone()).collect())
     }
 
     /// Get the home directory (eg., homepage containing HOME.html, INDEX.html) for the given service.
+    /// If the service is not explicitly registered, the wildcard service is used if there is one.
     pub fn get_service_home_directory_key(&self,
                                           long_name           : &String,
                                           service_name        : &String,
                                           data_decryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
                                                                         &::sodiumoxide::crypto::box_::SecretKey,
                                                                         &::sodiumoxide::crypto::box_::Nonce)>) -> Result<::safe_nfs::metadata::directory_key::DirectoryKey, ::errors::DnsError> {
-        // Allow unregistered clients to access this function
-        match self.find_dns_record(long_name) {
-            Ok(_) => (),
-            Err(::errors::DnsError::CoreError(::safe_core::errors::CoreError::OperationForbiddenForClient)) => (),
-            Err(::errors::DnsError::NfsError(::safe_nfs::errors::NfsError::CoreError(::safe_core::errors::CoreError::OperationForbiddenForClient))) => (),
-            Err(error) => return Err(error),
-        };
+        self.get_service_home_directory_key_and_label(long_name, service_name, data_decryption_keys).map(|(directory_key, _)| directory_key)
+    }
 
-        let (_, dns_record) = try!(self.get_housing_structured_data_and_dns_record(long_name, data_decryption_keys));
-        dns_record.services.get(service_name).map(|v| v.clone()).ok_or(::errors::DnsError::ServiceNotFound)
+    /// Same as `get_service_home_directory_key`, but also returns the label that was matched if the
+    /// wildcard service had to be used, eg., `user-1` for `user-1.example.com`.
+    pub fn get_service_home_directory_key_and_label(&self,
+                                                    long_name           : &String,
+                                                    service_name        : &String,
+                                                    data_decryption_keys: DataEncryptionKeys) -> Result<(::safe_nfs::metadata::directory_key::DirectoryKey,
+                                                                                                         Option<String>), ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        let (service, wildcard_label) = try!(linked_service::find_service(self, &dns_record, service_name));
+        match *try!(service.get_primary_target()) {
+            ServiceTarget::Directory(ref directory_key) => Ok((directory_key.clone(), wildcard_label)),
+            _ => Err(::errors::DnsError::ServiceIsNotADirectory),
+        }
+    }
+
+    /// Get what the given service points to - a home directory, a single file, immutable data or a
+    /// redirect to another url.
+    pub fn get_service_target(&self,
+                              long_name           : &String,
+                              service_name        : &String,
+                              data_decryption_keys: DataEncryptionKeys) -> Result<ServiceTarget, ::errors::DnsError> {
+        self.get_service_info(long_name, service_name, data_decryption_keys).map(|(target, _)| target)
+    }
+
+    /// Get what the given service points to along with the metadata describing it, if any.
+    pub fn get_service_info(&self,
+                            long_name           : &String,
+                            service_name        : &String,
+                            data_decryption_keys: DataEncryptionKeys) -> Result<(ServiceTarget, Option<ServiceInfo>), ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        let (service, _) = try!(linked_service::find_service(self, &dns_record, service_name));
+        Ok((try!(service.get_primary_target()).clone(), service.info.clone()))
+    }
+
+    /// Get all the mirrors serving the given service, primary first.
+    pub fn get_service_targets(&self,
+                               long_name           : &String,
+                               service_name        : &String,
+                               data_decryption_keys: DataEncryptionKeys) -> Result<Vec<ServiceTarget>, ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        linked_service::find_service(self, &dns_record, service_name).map(|(service, _)| service.targets)
     }
 
     /// Add a new service for the given Dns-name.
@@ -177,10 +411,26 @@ impl DnsOperations {
                        data_encryption_decryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
                                                                 &::sodiumoxide::crypto::box_::SecretKey,
                                                                 &::sodiumoxide::crypto::box_::Nonce)>) -> Result<StructuredData, ::errors::DnsError> {
+        self.add_service_target(long_name,
+                                (new_service.0, ServiceTarget::Directory(new_service.1)),
+                                private_signing_key,
+                                data_encryption_decryption_keys)
+    }
+
+    /// Add a new service for the given Dns-name pointing to any kind of target, eg., a redirect
+    /// to another url.
+    pub fn add_service_target(&self,
+                              long_name                      : &String,
+                              new_service                    : (String, ServiceTarget),
+                              private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validation::validate_service_name(&new_service.0));
+        try!(validation::validate_service_target(&new_service.1));
         self.add_remove_service_impl(long_name, (new_service.0, Some(new_service.1)), private_signing_key, data_encryption_decryption_keys)
     }
 
-    /// Remove a service from the given Dns-name.
+    /// Remove a service from the given Dns-name. A linked service is unlinked, leaving the
+    /// structured data housing it to its owners.
     pub fn remove_service(&self,
                           long_name                      : &String,
                           service_to_remove              : String,
@@ -191,47 +441,338 @@ impl DnsOperations {
         self.add_remove_service_impl(long_name, (service_to_remove, None), private_signing_key, data_encryption_decryption_keys)
     }
 
+    /// Add a mirror to an existing service of the given Dns-name. Mirrors are tried in the order
+    /// they were added, after the primary target the service was created with. Linked services
+    /// are edited by their owners through `update_linked_service`.
+    pub fn add_service_mirror(&self,
+                              long_name                      : &String,
+                              service_name                   : &String,
+                              mirror                         : ServiceTarget,
+                              private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validation::validate_service_target(&mirror));
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            let service = try!(get_own_service_mut(dns_record, service_name));
+            if service.targets.contains(&mirror) {
+                return Err(::errors::DnsError::ServiceAlreadyExists)
+            }
+            debug!("Adding service mirror ...");
+            service.targets.push(mirror);
+            Ok(())
+        })
+    }
+
+    /// Remove a mirror from a service of the given Dns-name. The last remaining target of a
+    /// service can not be removed this way - remove the service instead. Linked services are
+    /// edited by their owners through `update_linked_service`.
+    pub fn remove_service_mirror(&self,
+                                 long_name                      : &String,
+                                 service_name                   : &String,
+                                 mirror                         : &ServiceTarget,
+                                 private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                                 data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            let service = try!(get_own_service_mut(dns_record, service_name));
+            let pos = try!(service.targets.iter().position(|target| *target == *mirror).ok_or(::errors::DnsError::ServiceTargetNotFound));
+            if service.targets.len() == 1 {
+                return Err(::errors::DnsError::InvalidServiceTarget)
+            }
+            debug!("Removing service mirror at {:?} position ...", pos);
+            let _ = service.targets.remove(pos);
+            Ok(())
+        })
+    }
+
+    /// Set or clear (with `None`) the metadata describing a service of the given Dns-name. Linked
+    /// services are edited by their owners through `update_linked_service`.
+    pub fn set_service_info(&self,
+                            long_name                      : &String,
+                            service_name                   : &String,
+                            info                           : Option<ServiceInfo>,
+                            private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                            data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        if let Some(ref info) = info {
+            try!(validation::validate_service_info(info));
+        }
+
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            let service = try!(get_own_service_mut(dns_record, service_name));
+            debug!("Setting service info ...");
+            service.info = info;
+            Ok(())
+        })
+    }
+
+    /// Get the service used when the given Dns-name is resolved without a service label. This is
+    /// `DEFAULT_SERVICE_NAME` unless the record declares otherwise.
+    pub fn get_default_service(&self,
+                               long_name           : &String,
+                               data_decryption_keys: DataEncryptionKeys) -> Result<String, ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        Ok(dns_record.default_service.unwrap_or(DEFAULT_SERVICE_NAME.to_string()))
+    }
+
+    /// Declare (or with `None` stop declaring) the service used when the given Dns-name is
+    /// resolved without a service label. The service must exist. Removing the service clears this.
+    pub fn set_default_service(&self,
+                               long_name                      : &String,
+                               service_name                   : Option<String>,
+                               private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                               data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            if let Some(ref service_name) = service_name {
+                if !dns_record.services.contains_key(service_name) && !dns_record.service_links.contains_key(service_name) {
+                    return Err(::errors::DnsError::ServiceNotFound)
+                }
+            }
+            debug!("Setting default service to {:?} ...", service_name);
+            dns_record.default_service = service_name;
+            Ok(())
+        })
+    }
+
+    /// Set (or with `None` clear) the service answering for every label that is not explicitly
+    /// registered as a service of the given Dns-name, ie., `*.example.com`.
+    pub fn set_wildcard_service(&self,
+                                long_name                      : &String,
+                                target                         : Option<ServiceTarget>,
+                                private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                                data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        if let Some(ref target) = target {
+            try!(validation::validate_service_target(target));
+        }
+
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            debug!("Setting wildcard service ...");
+            dns_record.wildcard_service = target.map(Service::new);
+            Ok(())
+        })
+    }
+
+    /// Delegate a sub-label of the given Dns-name to another owner, eg., `team-a` of `example.com`
+    /// so that `team-a.example.com` is administered by the owners of the delegated record.
+    pub fn add_delegation(&self,
+                          long_name                      : &String,
+                          new_delegation                 : (String, Delegation),
+                          private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                          data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validation::validate_service_name(&new_delegation.0));
+        self.add_remove_delegation_impl(long_name, (new_delegation.0, Some(new_delegation.1)), private_signing_key, data_encryption_decryption_keys)
+    }
+
+    /// Remove the delegation of a sub-label from the given Dns-name.
+    pub fn remove_delegation(&self,
+                             long_name                      : &String,
+                             delegation_to_remove           : String,
+                             private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                             data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        self.add_remove_delegation_impl(long_name, (delegation_to_remove, None), private_signing_key, data_encryption_decryption_keys)
+    }
+
+    /// Make the given Dns-name an alias of another fully qualified name, eg., after a rebrand
+    /// `oldname.com` can be resolved as `newname.com` and `blog.oldname.com` as
+    /// `blog.newname.com`. Passing `None` turns the alias off again.
+    pub fn set_alias(&self,
+                     long_name                      : &String,
+                     alias                          : Option<String>,
+                     private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                     data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        if let Some(ref target) = alias {
+            if try!(validation::split_name_labels(target)).len() < MIN_REGISTERED_NAME_LABELS {
+                return Err(::errors::DnsError::InvalidDnsName)
+            }
+            if *target == *long_name {
+                return Err(::errors::DnsError::AliasLoop)
+            }
+        }
+
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            debug!("Setting alias to {:?} ...", alias);
+            dns_record.alias = alias;
+            Ok(())
+        })
+    }
+
+    /// Get the keys of all the text records (eg., verification tokens, contact info) attached to
+    /// this Dns-name
+    pub fn get_all_text_records(&self,
+                                long_name           : &String,
+                                data_decryption_keys: DataEncryptionKeys) -> Result<Vec<String>, ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        Ok(dns_record.text_records.keys().map(|a| a.clone()).collect())
+    }
+
+    /// Get the value of a text record attached to this Dns-name
+    pub fn get_text_record(&self,
+                           long_name           : &String,
+                           key                 : &String,
+                           data_decryption_keys: DataEncryptionKeys) -> Result<String, ::errors::DnsError> {
+        let dns_record = try!(self.get_readable_dns_record(long_name, data_decryption_keys));
+        dns_record.text_records.get(key).map(|v| v.clone()).ok_or(::errors::DnsError::TextRecordNotFound)
+    }
+
+    /// Attach a new text record to the given Dns-name.
+    pub fn add_text_record(&self,
+                           long_name                      : &String,
+                           new_record                     : (String, String),
+                           private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                           data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validation::validate_text_record(&new_record.0, &new_record.1));
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            if dns_record.text_records.contains_key(&new_record.0) {
+                return Err(::errors::DnsError::TextRecordAlreadyExists)
+            }
+            if dns_record.text_records.len() == validation::MAX_TEXT_RECORDS {
+                return Err(::errors::DnsError::InvalidTextRecord)
+            }
+            debug!("Inserting text record ...");
+            let _ = dns_record.text_records.insert(new_record.0, new_record.1);
+            Ok(())
+        })
+    }
+
+    /// Change the value of an existing text record of the given Dns-name.
+    pub fn update_text_record(&self,
+                              long_name                      : &String,
+                              record                         : (String, String),
+                              private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        try!(validation::validate_text_record(&record.0, &record.1));
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            let value = try!(dns_record.text_records.get_mut(&record.0).ok_or(::errors::DnsError::TextRecordNotFound));
+            debug!("Updating text record ...");
+            *value = record.1;
+            Ok(())
+        })
+    }
+
+    /// Remove a text record from the given Dns-name.
+    pub fn remove_text_record(&self,
+                              long_name                      : &String,
+                              key                            : String,
+                              private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            debug!("Removing text record ...");
+            let _ = try!(dns_record.text_records.remove(&key).ok_or(::errors::DnsError::TextRecordNotFound));
+            Ok(())
+        })
+    }
+
+    /// Fetch the Dns record of any name, including ones not registered by this user. This is
+    /// usable by unregistered clients too.
+    fn get_readable_dns_record(&self,
+                               long_name           : &String,
+                               data_decryption_keys: DataEncryptionKeys) -> Result<Dns, ::errors::DnsError> {
+        // Allow unregistered clients to access this function
+        match self.find_dns_record(long_name) {
+            Ok(_) => (),
+            Err(::errors::DnsError::CoreError(::safe_core::errors::CoreError::OperationForbiddenForClient)) => (),
+            Err(::errors::DnsError::NfsError(::safe_nfs::errors::NfsError::CoreError(::safe_core::errors::CoreError::OperationForbiddenForClient))) => (),
+            Err(error) => return Err(error),
+        };
+
+        let (_, dns_record) = try!(self.get_housing_structured_data_and_dns_record(long_name, data_decryption_keys));
+        // Expired names are only accessible to their owners through renewal
+        if dns_record.is_expired() {
+            return Err(::errors::DnsError::DnsRecordExpired)
+        }
+        Ok(dns_record)
+    }
+
     fn find_dns_record(&self, long_name: &String) -> Result<dns_configuration::DnsConfiguation, ::errors::DnsError> {
-        let config_vec = try!(dns_configuration::get_dns_configuaration_data(self.client.clone()));
+        let config_vec = try!(dry_run::get_dns_configuration(self));
         config_vec.iter().find(|config| config.long_name == *long_name).map(|v| v.clone()).ok_or(::errors::DnsError::DnsRecordNotFound)
     }
 
     fn add_remove_service_impl(&self,
                                long_name                      : &String,
-                               service                        : (String, Option<::safe_nfs::metadata::directory_key::DirectoryKey>),
+                               service                        : (String, Option<ServiceTarget>),
                                private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
                                data_encryption_decryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
                                                                         &::sodiumoxide::crypto::box_::SecretKey,
                                                                         &::sodiumoxide::crypto::box_::Nonce)>) -> Result<StructuredData, ::errors::DnsError> {
-        let _ = try!(self.find_dns_record(long_name));
-
-        let is_add_service = service.1.is_some();
-        let (prev_struct_data, mut dns_record) = try!(self.get_housing_structured_data_and_dns_record(long_name,
-                                                                                                      data_encryption_decryption_keys));
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            match service.1 {
+                Some(target) => {
+                    if dns_record.services.contains_key(&service.0) ||
+                       dns_record.service_links.contains_key(&service.0) ||
+                       dns_record.delegations.contains_key(&service.0) {
+                        return Err(::errors::DnsError::ServiceAlreadyExists)
+                    }
+                    debug!("Inserting service ...");
+                    let _ = dns_record.services.insert(service.0, Service::new(target));
+                },
+                None => {
+                    debug!("Removing service ...");
+                    if dns_record.services.remove(&service.0).is_none() {
+                        let _ = try!(dns_record.service_links.remove(&service.0).ok_or(::errors::DnsError::ServiceNotFound));
+                    }
+                    if dns_record.default_service.as_ref() == Some(&service.0) {
+                        dns_record.default_service = None;
+                    }
+                },
+            }
+            Ok(())
+        })
+    }
 
-        if !is_add_service && !dns_record.services.contains_key(&service.0) {
-            Err(::errors::DnsError::ServiceNotFound)
-        } else if is_add_service && dns_record.services.contains_key(&service.0) {
-            Err(::errors::DnsError::ServiceAlreadyExists)
-        } else {
-            if is_add_service {
-                debug!("Inserting service ...");
-                let _ = dns_record.services.insert(service.0, try!(service.1.ok_or(::errors::DnsError::from("Programming Error - Investigate !!"))));
-            } else {
-                debug!("Removing service ...");
-                let _ = dns_record.services.remove(&service.0);
+    fn add_remove_delegation_impl(&self,
+                                  long_name                      : &String,
+                                  delegation                     : (String, Option<Delegation>),
+                                  private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                                  data_encryption_decryption_keys: DataEncryptionKeys) -> Result<StructuredData, ::errors::DnsError> {
+        self.update_dns_record(long_name, private_signing_key, data_encryption_decryption_keys, |dns_record| {
+            match delegation.1 {
+                Some(target) => {
+                    if dns_record.delegations.contains_key(&delegation.0) ||
+                       dns_record.services.contains_key(&delegation.0) ||
+                       dns_record.service_links.contains_key(&delegation.0) {
+                        return Err(::errors::DnsError::DelegationAlreadyExists)
+                    }
+                    debug!("Inserting delegation ...");
+                    let _ = dns_record.delegations.insert(delegation.0, target);
+                },
+                None => {
+                    debug!("Removing delegation ...");
+                    let _ = try!(dns_record.delegations.remove(&delegation.0).ok_or(::errors::DnsError::DelegationNotFound));
+                },
             }
+            Ok(())
+        })
+    }
+
+    /// Fetch the Dns record of a name registered by this user, let `modifier` change it and
+    /// return the structured data carrying the result as its next version.
+    fn update_dns_record<F>(&self,
+                            long_name                      : &String,
+                            private_signing_key            : &::sodiumoxide::crypto::sign::SecretKey,
+                            data_encryption_decryption_keys: DataEncryptionKeys,
+                            modifier                       : F) -> Result<StructuredData, ::errors::DnsError>
+                            where F: FnOnce(&mut Dns) -> Result<(), ::errors::DnsError> {
+        let _ = try!(self.find_dns_record(long_name));
 
-            Ok(try!(::safe_core::structured_data_operations::unversioned::create(self.client.clone(),
-                                                                                   DNS_TAG,
-                                                                                   prev_struct_data.get_identifier().clone(),
-                                                                                   prev_struct_data.get_version() + 1,
-                                                                                   try!(serialise(&dns_record)),
-                                                                                   prev_struct_data.get_owner_keys().clone(),
-                                                                                   prev_struct_data.get_previous_owner_keys().clone(),
-                                                                                   private_signing_key,
-                                                                                   data_encryption_decryption_keys)))
+        let prev_struct_data = try!(self.get_housing_structured_data(long_name));
+        let spilled_prev_dns_record = try!(self.read_spilled_dns_record(&prev_struct_data, data_encryption_decryption_keys));
+        // Records moved to another identifier are updated through an instance deriving the new one
+        if spilled_prev_dns_record.forwarded_to.is_some() {
+            return Err(::errors::DnsError::DnsRecordMoved)
         }
+        let prev_services_overflow = spilled_prev_dns_record.services_overflow.clone();
+        let mut dns_record = spilled_prev_dns_record.clone();
+        try!(overflow::load_spilled_services(self, &mut dns_record, data_encryption_decryption_keys));
+        try!(modifier(&mut dns_record));
+        try!(history::record_history(self, prev_struct_data.get_version(), spilled_prev_dns_record, &mut dns_record, data_encryption_decryption_keys));
+
+        dry_run::create_structured_data(self,
+                                        prev_struct_data.get_identifier().clone(),
+                                        prev_struct_data.get_version() + 1,
+                                        Some(&dns_record),
+                                        &prev_services_overflow,
+                                        prev_struct_data.get_owner_keys().clone(),
+                                        prev_struct_data.get_previous_owner_keys().clone(),
+                                        private_signing_key,
+                                        data_encryption_decryption_keys)
     }
 
     fn get_housing_structured_data_and_dns_record(&self,
@@ -241,35 +782,147 @@ impl DnsOperations {
                                                                                 &::sodiumoxide::crypto::box_::Nonce)>) -> Result<(StructuredData,
                                                                                                                                   Dns), ::errors::DnsError> {
         let struct_data = try!(self.get_housing_structured_data(long_name));
-        let dns_record = try!(deserialise(&try!(::safe_core::structured_data_operations::unversioned::get_data(self.client.clone(),
-                                                                                                                                         &struct_data,
-                                                                                                                                         data_decryption_keys))));
-        Ok((struct_data, dns_record))
+        let dns_record = try!(self.read_dns_record(&struct_data, data_decryption_keys));
+        self.follow_forwarding(struct_data, dns_record, data_decryption_keys)
+    }
+
+    fn get_structured_data_and_dns_record(&self,
+                                          identifier          : XorName,
+                                          data_decryption_keys: DataEncryptionKeys) -> Result<(StructuredData,
+                                                                                               Dns), ::errors::DnsError> {
+        let struct_data = try!(self.get_structured_data(identifier));
+        let dns_record = try!(self.read_dns_record(&struct_data, data_decryption_keys));
+        self.follow_forwarding(struct_data, dns_record, data_decryption_keys)
     }
 
+    /// The structured data and Dns record the given Dns record forwards to, if it was moved by a
+    /// migration, otherwise the given ones
+    fn follow_forwarding(&self,
+                         struct_data         : StructuredData,
+                         dns_record          : Dns,
+                         data_decryption_keys: DataEncryptionKeys) -> Result<(StructuredData, Dns), ::errors::DnsError> {
+        let forwarded_identifier = match dns_record.forwarded_to {
+            Some(ref forwarded_identifier) => forwarded_identifier.clone(),
+            None => return Ok((struct_data, dns_record)),
+        };
+
+        debug!("Following forwarding of migrated dns record ...");
+        let forwarded_struct_data = try!(self.get_structured_data(forwarded_identifier));
+        // Only the owners of the name can have moved it
+        if forwarded_struct_data.get_owner_keys() != struct_data.get_owner_keys() {
+            return Err(::errors::DnsError::ForwardingOwnerMismatch)
+        }
+        let forwarded_dns_record = try!(self.read_dns_record(&forwarded_struct_data, data_decryption_keys));
+        if forwarded_dns_record.forwarded_to.is_some() {
+            return Err(::errors::DnsError::from("Forwarded dns record is forwarded again"))
+        }
+        Ok((forwarded_struct_data, forwarded_dns_record))
+    }
+
+    fn read_dns_record(&self,
+                       struct_data         : &StructuredData,
+                       data_decryption_keys: DataEncryptionKeys) -> Result<Dns, ::errors::DnsError> {
+        let mut dns_record = try!(self.read_spilled_dns_record(struct_data, data_decryption_keys));
+        try!(overflow::load_spilled_services(self, &mut dns_record, data_decryption_keys));
+        Ok(dns_record)
+    }
+
+    /// Dns record as carried by the structured data, ie., with the services possibly still in
+    /// overflow chunks
+    fn read_spilled_dns_record(&self,
+                               struct_data         : &StructuredData,
+                               data_decryption_keys: DataEncryptionKeys) -> Result<Dns, ::errors::DnsError> {
+        Dns::decode(&try!(self.storage.get_structured_data_content(struct_data, data_decryption_keys)))
+    }
+
+    /// Structured data housing the Dns record of the given name. Instances requiring commitments
+    /// look it up through the claims of the name, see `commitment::get_honoured_structured_data`.
     fn get_housing_structured_data(&self, long_name: &String) -> Result<StructuredData, ::errors::DnsError> {
-        let identifier = XorName::new(::sodiumoxide::crypto::hash::sha512::hash(long_name.as_bytes()).0);
-        let request = DataRequest::Structured(identifier, DNS_TAG);
         debug!("Retrieving structured data from network for {:?} dns ...", long_name);
-        let response_getter = try!(unwrap_result!(self.client.lock()).get(request, None));
-        if let Data::Structured(struct_data) = try!(response_getter.get()) {
-            Ok(struct_data)
+        match self.min_commitment_age {
+            Some(_) => commitment::get_honoured_structured_data(self, long_name),
+            None => self.get_structured_data(self.get_identifier(long_name)),
+        }
+    }
+
+    fn get_immutable_data(&self, name: XorName) -> Result<ImmutableData, ::errors::DnsError> {
+        self.storage.get_immutable_data(name)
+    }
+
+    fn put_immutable_data(&self, content: Vec<u8>) -> Result<XorName, ::errors::DnsError> {
+        let immutable_data = ImmutableData::new(ImmutableDataType::Normal, content);
+        let name = immutable_data.name();
+        if self.dry_run.is_some() {
+            debug!("Dry run - not storing immutable data ...");
         } else {
-            Err(::errors::DnsError::from(::safe_core::errors::CoreError::ReceivedUnexpectedData))
+            try!(self.storage.put_immutable_data(immutable_data));
+        }
+        Ok(name)
+    }
+
+    /// Store the content as immutable data, encrypted if keys are given. Every blob is sealed
+    /// with a fresh random nonce stored in front of it - the nonce of the keys is only used for
+    /// the structured data.
+    fn put_encrypted_immutable_data(&self,
+                                    content             : Vec<u8>,
+                                    data_encryption_keys: DataEncryptionKeys) -> Result<XorName, ::errors::DnsError> {
+        match data_encryption_keys {
+            Some((public_key, secret_key, _)) => {
+                let nonce = ::sodiumoxide::crypto::box_::gen_nonce();
+                let mut sealed_content = nonce.0.to_vec();
+                sealed_content.extend(::sodiumoxide::crypto::box_::seal(&content, &nonce, public_key, secret_key));
+                self.put_immutable_data(sealed_content)
+            },
+            None => self.put_immutable_data(content),
         }
     }
+
+    /// Fetch content stored through `put_encrypted_immutable_data`
+    fn get_decrypted_immutable_data(&self,
+                                    name                : XorName,
+                                    data_decryption_keys: DataEncryptionKeys) -> Result<Vec<u8>, ::errors::DnsError> {
+        let content = try!(self.get_immutable_data(name)).value().clone();
+        match data_decryption_keys {
+            Some((public_key, secret_key, _)) => {
+                if content.len() < ::sodiumoxide::crypto::box_::NONCEBYTES {
+                    return Err(::errors::DnsError::from("Could not decrypt immutable data"))
+                }
+                let (nonce, sealed_content) = content.split_at(::sodiumoxide::crypto::box_::NONCEBYTES);
+                let nonce = try!(::sodiumoxide::crypto::box_::Nonce::from_slice(nonce).ok_or(::errors::DnsError::from("Could not decrypt immutable data")));
+                ::sodiumoxide::crypto::box_::open(sealed_content, &nonce, public_key, secret_key)
+                    .map_err(|_| ::errors::DnsError::from("Could not decrypt immutable data"))
+            },
+            None => Ok(content),
+        }
+    }
+
+    fn get_structured_data(&self, identifier: XorName) -> Result<StructuredData, ::errors::DnsError> {
+        let tag = self.namespace.get_tag();
+        self.get_tagged_structured_data(identifier, tag)
+    }
+
+    fn get_tagged_structured_data(&self, identifier: XorName, tag: u64) -> Result<StructuredData, ::errors::DnsError> {
+        self.storage.get_structured_data(identifier, tag)
+    }
+
+    /// Identifier of the structured data housing the Dns record registered under `long_name`
+    fn get_identifier(&self, long_name: &String) -> XorName {
+        self.namespace.get_identifier(long_name.as_bytes())
+    }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, RustcEncodable, RustcDecodable)]
-struct Dns {
-    long_name     : String,
-    services      : ::std::collections::HashMap<String, ::safe_nfs::metadata::directory_key::DirectoryKey>,
-    encryption_key: ::sodiumoxide::crypto::box_::PublicKey,
+/// Service of the Dns record which is edited through the record itself, ie., which is not linked
+fn get_own_service_mut<'a>(dns_record: &'a mut Dns, service_name: &String) -> Result<&'a mut Service, ::errors::DnsError> {
+    if dns_record.service_links.contains_key(service_name) {
+        return Err(::errors::DnsError::ServiceIsLinked)
+    }
+    dns_record.services.get_mut(service_name).ok_or(::errors::DnsError::ServiceNotFound)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use super::test_utils;
     use xor_name::XorName;
     use routing::Data;
 
@@ -278,20 +931,20 @@ mod test {
         let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
         let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
 
-        let dns_name = unwrap_result!(::safe_core::utility::generate_random_string(10));
+        let dns_name = test_utils::generate_random_dns_name();
         let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
         let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
 
         let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
 
         // Register
-        let mut struct_data = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
-                                                                       &messaging_keypair.0,
-                                                                       &messaging_keypair.1,
-                                                                       &vec![],
-                                                                       owners.clone(),
-                                                                       &secret_signing_key,
-                                                                       None));
+        let (mut struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            &vec![],
+                                                                            owners.clone(),
+                                                                            &secret_signing_key,
+                                                                            None));
 
         unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
 
@@ -312,8 +965,23 @@ mod test {
             Err(error) => panic!("{:?}", error),
         }
 
+        // Names must have at least two valid labels
+        for invalid_name in vec!["com".to_string(), "my site.com".to_string()] {
+            match dns_operations.register_dns(invalid_name,
+                                              &messaging_keypair.0,
+                                              &messaging_keypair.1,
+                                              &vec![],
+                                              owners.clone(),
+                                              &secret_signing_key,
+                                              None) {
+                Ok(_) => panic!("Should have been an error"),
+                Err(::errors::DnsError::InvalidDnsName) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+
         // Delete
-        struct_data = unwrap_result!(dns_operations.delete_dns(&dns_name, &secret_signing_key));
+        struct_data = unwrap_result!(dns_operations.delete_dns(&dns_name, &secret_signing_key)).0;
         unwrap_result!(unwrap_result!(client.lock()).delete(Data::Structured(struct_data), None));
 
         // Registering again should be allowed
@@ -331,7 +999,7 @@ mod test {
         let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
         let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
 
-        let dns_name = unwrap_result!(::safe_core::utility::generate_random_string(10));
+        let dns_name = test_utils::generate_random_dns_name();
         let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
 
         let mut services = vec![("www".to_string(),
@@ -355,13 +1023,13 @@ mod test {
         let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
 
         // Register
-        let mut struct_data = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
-                                                                       &messaging_keypair.0,
-                                                                       &messaging_keypair.1,
-                                                                       &services,
-                                                                       owners.clone(),
-                                                                       &secret_signing_key,
-                                                                       None));
+        let (mut struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            &services,
+                                                                            owners.clone(),
+                                                                            &secret_signing_key,
+                                                                            None));
 
         unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
 
@@ -422,4 +1090,175 @@ mod test {
         assert_eq!(services.len(), services_vec.len());
         assert!(services.iter().all(|&(ref a, _)| services_vec.iter().find(|b| *a == **b).is_some()));
     }
+
+    #[test]
+    fn describe_services() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+        let (mut struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            &vec![("www".to_string(), home_dir_key.clone())],
+                                                                            owners,
+                                                                            &secret_signing_key,
+                                                                            None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        let unregistered_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::client::Client::create_unregistered_client())));
+        let dns_operations_unregistered = DnsOperations::new_unregistered(unregistered_client);
+
+        let mut info = ServiceInfo::new(ServiceKind::Website);
+        info.description = "Home page".to_string();
+        info.content_hints.push("text/html".to_string());
+        struct_data = unwrap_result!(dns_operations.set_service_info(&dns_name, &"www".to_string(), Some(info.clone()), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let (target, fetched_info) = unwrap_result!(dns_operations_unregistered.get_service_info(&dns_name, &"www".to_string(), None));
+        assert_eq!(target, ServiceTarget::Directory(home_dir_key));
+        assert_eq!(fetched_info, Some(info));
+    }
+
+    #[test]
+    fn redirect_services() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+        let (mut struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            &vec![("www".to_string(), home_dir_key.clone())],
+                                                                            owners,
+                                                                            &secret_signing_key,
+                                                                            None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        let unregistered_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::client::Client::create_unregistered_client())));
+        let dns_operations_unregistered = DnsOperations::new_unregistered(unregistered_client);
+
+        let redirect = ServiceTarget::Redirect("safe:www.newname.com/blog".to_string());
+        struct_data = unwrap_result!(dns_operations.add_service_target(&dns_name, ("old-blog".to_string(), redirect.clone()), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        assert_eq!(unwrap_result!(dns_operations_unregistered.get_service_target(&dns_name, &"old-blog".to_string(), None)), redirect);
+        match dns_operations_unregistered.get_service_home_directory_key(&dns_name, &"old-blog".to_string(), None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::ServiceIsNotADirectory) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+        assert_eq!(unwrap_result!(dns_operations_unregistered.get_service_home_directory_key(&dns_name, &"www".to_string(), None)),
+                   home_dir_key);
+    }
+
+    #[test]
+    fn wildcard_services() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+        let (mut struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            &vec![("www".to_string(), home_dir_key.clone())],
+                                                                            owners,
+                                                                            &secret_signing_key,
+                                                                            None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        let unregistered_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::client::Client::create_unregistered_client())));
+        let dns_operations_unregistered = DnsOperations::new_unregistered(unregistered_client);
+
+        // Wildcard service answers for unregistered labels while explicit services still win
+        let wildcard_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([127; 64]),
+                                                                                     15000,
+                                                                                     false,
+                                                                                     ::safe_nfs::AccessLevel::Public);
+        struct_data = unwrap_result!(dns_operations.set_wildcard_service(&dns_name,
+                                                                       Some(ServiceTarget::Directory(wildcard_dir_key.clone())),
+                                                                       &secret_signing_key,
+                                                                       None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let (dir_key, label) = unwrap_result!(dns_operations_unregistered.get_service_home_directory_key_and_label(&dns_name, &"user-1".to_string(), None));
+        assert_eq!(dir_key, wildcard_dir_key);
+        assert_eq!(label, Some("user-1".to_string()));
+
+        let (dir_key, label) = unwrap_result!(dns_operations_unregistered.get_service_home_directory_key_and_label(&dns_name, &"www".to_string(), None));
+        assert_eq!(dir_key, home_dir_key);
+        assert_eq!(label, None);
+    }
+
+    #[test]
+    fn manipulate_text_records() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let (mut struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            &vec![],
+                                                                            owners,
+                                                                            &secret_signing_key,
+                                                                            None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        let unregistered_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::client::Client::create_unregistered_client())));
+        let dns_operations_unregistered = DnsOperations::new_unregistered(unregistered_client);
+
+        let key = "safe-verification".to_string();
+
+        // Add
+        struct_data = unwrap_result!(dns_operations.add_text_record(&dns_name, (key.clone(), "token-0".to_string()), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations_unregistered.get_all_text_records(&dns_name, None)), vec![key.clone()]);
+        assert_eq!(unwrap_result!(dns_operations_unregistered.get_text_record(&dns_name, &key, None)), "token-0".to_string());
+
+        // Adding again is not allowed
+        match dns_operations.add_text_record(&dns_name, (key.clone(), "token-1".to_string()), &secret_signing_key, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::TextRecordAlreadyExists) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+
+        // Update
+        struct_data = unwrap_result!(dns_operations.update_text_record(&dns_name, (key.clone(), "token-1".to_string()), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations_unregistered.get_text_record(&dns_name, &key, None)), "token-1".to_string());
+
+        // Remove
+        struct_data = unwrap_result!(dns_operations.remove_text_record(&dns_name, key.clone(), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        assert_eq!(unwrap_result!(dns_operations_unregistered.get_all_text_records(&dns_name, None)).len(), 0);
+    }
 }
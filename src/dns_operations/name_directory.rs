@@ -0,0 +1,446 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+use routing::StructuredData;
+use xor_name::XorName;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use super::DnsOperations;
+
+/// Tag of the structured data listing a Dns-name in a name directory
+pub const NAME_DIRECTORY_TAG: u64 = 9;
+
+/// Characters names are grouped by in a name directory; names starting with any other character
+/// share one group
+const BUCKET_CHARS: &'static str = "0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Maximum number of names returned by a single search of a name directory
+pub const MAX_DIRECTORY_PAGE_SIZE: usize = 100;
+
+/// Maximum number of structured data read by a single search of a name directory, or while
+/// looking for a slot to list a Dns-name in
+pub const MAX_DIRECTORY_SEARCH_READS: u64 = 1000;
+
+/// Maximum length of a Dns-name listed in a name directory
+pub const MAX_LISTED_NAME_LENGTH: usize = 256;
+
+/// Which names of a name directory a search returns
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NameFilter {
+    /// Every name
+    All,
+    /// Names starting with the given string
+    Prefix(String),
+    /// Names containing the given string anywhere
+    Substring(String),
+}
+
+impl NameFilter {
+    fn matches(&self, long_name: &String) -> bool {
+        match *self {
+            NameFilter::All => true,
+            NameFilter::Prefix(ref prefix) => long_name.starts_with(prefix),
+            NameFilter::Substring(ref substring) => long_name.contains(substring),
+        }
+    }
+}
+
+/// One page of the names of a name directory matching a search
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NamePage {
+    /// Matching names, in lexicographic order
+    pub names      : Vec<String>,
+    /// Offset to pass to get the following page, `None` if this is the last one
+    pub next_offset: Option<usize>,
+    /// Whether the search stopped after `MAX_DIRECTORY_SEARCH_READS` reads, leaving part of the
+    /// directory unlooked at
+    pub truncated  : bool,
+}
+
+/// Slot of a name directory and the Dns-name it lists, `None` if it lists none or is unreadable
+type DirectorySlot = (StructuredData, Option<String>);
+
+impl DnsOperations {
+    /// Get an instance listing the Dns-names it registers in the given public name directory,
+    /// see `search_name_directory`. The slot listing a name is returned by `register_dns`,
+    /// `delete_dns` and `transfer_dns` along with the Dns record, to be stored by the caller.
+    pub fn with_name_directory(mut self, directory_name: String) -> DnsOperations {
+        self.name_directory = Some(directory_name);
+        self
+    }
+
+    /// Get a page of the names of the given name directory matching the filter, starting at the
+    /// `offset`-th match. At most `page_size` names are returned, which must not exceed
+    /// `MAX_DIRECTORY_PAGE_SIZE`. Usable by unregistered clients too.
+    /// Each name is listed in its own slot, a structured data signed by the owners of the name.
+    /// Slots are grouped by the first character of the name they list, so a search by prefix
+    /// only looks at one group. As anyone can fill a slot, those listing a name whose Dns record
+    /// is not owned by the owners of the slot are skipped.
+    pub fn search_name_directory(&self,
+                                 directory_name: &String,
+                                 filter        : &NameFilter,
+                                 offset        : usize,
+                                 page_size     : usize) -> Result<NamePage, ::errors::DnsError> {
+        if page_size == 0 || page_size > MAX_DIRECTORY_PAGE_SIZE {
+            return Err(::errors::DnsError::InvalidPageSize)
+        }
+
+        let buckets: Vec<String> = match *filter {
+            NameFilter::Prefix(ref prefix) if !prefix.is_empty() => vec![get_bucket(prefix)],
+            _ => BUCKET_CHARS.chars().map(|c| c.to_string()).chain(Some(String::new())).collect(),
+        };
+
+        debug!("Searching name directory {:?} ...", directory_name);
+        let mut reads = 0;
+        let mut truncated = false;
+        let mut names = Vec::new();
+        'buckets: for bucket in buckets.iter() {
+            for index in 0.. {
+                if !take_read(&mut reads) {
+                    truncated = true;
+                    break 'buckets
+                }
+                let (struct_data, listed_name) = match try!(self.get_directory_slot(directory_name, bucket, index)) {
+                    Some(slot) => slot,
+                    None => break,
+                };
+
+                if let Some(long_name) = listed_name {
+                    if filter.matches(&long_name) {
+                        if !take_read(&mut reads) {
+                            truncated = true;
+                            break 'buckets
+                        }
+                        if try!(self.is_listed_by_owners(&struct_data, &long_name)) {
+                            names.push(long_name);
+                        }
+                    }
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+
+        let mut matches = names.into_iter().skip(offset);
+        let page: Vec<String> = matches.by_ref().take(page_size).collect();
+        let next_offset = match matches.next() {
+            Some(_) => Some(offset + page.len()),
+            None => None,
+        };
+
+        Ok(NamePage {
+            names      : page,
+            next_offset: next_offset,
+            truncated  : truncated,
+        })
+    }
+
+    /// The `index`-th slot of the given group of the name directory, `None` if not taken. Slots
+    /// are taken one after the other, so the first absent one ends the group.
+    fn get_directory_slot(&self,
+                          directory_name: &String,
+                          bucket        : &String,
+                          index         : u64) -> Result<Option<DirectorySlot>, ::errors::DnsError> {
+        let identifier = get_directory_slot_identifier(&self.namespace, directory_name, bucket, index);
+        let struct_data = match self.get_tagged_structured_data(identifier, NAME_DIRECTORY_TAG) {
+            Ok(struct_data) => struct_data,
+            Err(ref error) if super::storage::is_data_not_found(error) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        let content = try!(self.storage.get_structured_data_content(&struct_data, None));
+        let listed_name = if content.is_empty() {
+            None
+        } else {
+            deserialise(&content).ok()
+        };
+        Ok(Some((struct_data, listed_name)))
+    }
+
+    /// Whether the Dns record of the name is owned by the owners of the slot listing it
+    fn is_listed_by_owners(&self, slot: &StructuredData, long_name: &String) -> Result<bool, ::errors::DnsError> {
+        match self.get_housing_structured_data(long_name) {
+            Ok(struct_data) => Ok(!struct_data.get_data().is_empty() && struct_data.get_owner_keys() == slot.get_owner_keys()),
+            Err(ref error) if super::storage::is_data_not_found(error) => Ok(false),
+            Err(::errors::DnsError::DnsRecordNotFound) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Slot listing the Dns-name in the name directory of the instance, if it has one, eg., on
+/// `register_dns`. A slot of the same owners which no longer lists a name of theirs is reused,
+/// otherwise the first free one of the group is taken. `None` if the name is listed already.
+pub fn list_name(dns_operations     : &DnsOperations,
+                 long_name          : &String,
+                 owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                 private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<Option<StructuredData>, ::errors::DnsError> {
+    let directory_name = match dns_operations.name_directory {
+        Some(ref directory_name) => directory_name,
+        None => return Ok(None),
+    };
+    if long_name.len() > MAX_LISTED_NAME_LENGTH {
+        return Err(::errors::DnsError::ListedNameTooLong)
+    }
+
+    let bucket = get_bucket(long_name);
+    let mut reads = 0;
+    let mut reusable_slot = None;
+    let mut free_index = None;
+    let mut index = 0;
+    while take_read(&mut reads) {
+        let (struct_data, listed_name) = match try!(dns_operations.get_directory_slot(directory_name, &bucket, index)) {
+            Some(slot) => slot,
+            None => {
+                free_index = Some(index);
+                break
+            },
+        };
+        index += 1;
+
+        if struct_data.get_owner_keys() != owners {
+            continue
+        }
+        match listed_name {
+            Some(ref listed_name) if listed_name == long_name => return Ok(None),
+            Some(ref listed_name) => if reusable_slot.is_none() && take_read(&mut reads) &&
+                                        !try!(dns_operations.is_listed_by_owners(&struct_data, listed_name)) {
+                reusable_slot = Some(struct_data);
+            },
+            None => if reusable_slot.is_none() {
+                reusable_slot = Some(struct_data);
+            },
+        }
+    }
+
+    let (identifier, version, prev_owners) = match (reusable_slot, free_index) {
+        (Some(prev_struct_data), _) => (prev_struct_data.get_identifier().clone(),
+                                        prev_struct_data.get_version() + 1,
+                                        prev_struct_data.get_previous_owner_keys().clone()),
+        (None, Some(index)) => (get_directory_slot_identifier(&dns_operations.namespace, directory_name, &bucket, index), 0, vec![]),
+        (None, None) => return Err(::errors::DnsError::NameDirectoryFull),
+    };
+
+    debug!("Listing {:?} in name directory ...", long_name);
+    Ok(Some(try!(super::dry_run::create_tagged_structured_data(dns_operations,
+                                                               NAME_DIRECTORY_TAG,
+                                                               identifier,
+                                                               version,
+                                                               try!(serialise(long_name)),
+                                                               owners.clone(),
+                                                               prev_owners,
+                                                               private_signing_key,
+                                                               None))))
+}
+
+/// Slot listing the Dns-name emptied, the counterpart of `list_name`, eg., on `delete_dns`. The
+/// slot is kept for the owners to list another name in.
+pub fn unlist_name(dns_operations     : &DnsOperations,
+                   long_name          : &String,
+                   owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                   private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<Option<StructuredData>, ::errors::DnsError> {
+    debug!("Unlisting {:?} from name directory ...", long_name);
+    update_listing(dns_operations, long_name, owners, private_signing_key, |_| (vec![], owners.clone()))
+}
+
+/// Slot listing the Dns-name handed over to the new owners of the name, eg., on `transfer_dns`,
+/// so the name keeps being found
+pub fn transfer_listed_name(dns_operations     : &DnsOperations,
+                            long_name          : &String,
+                            owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                            new_owners         : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                            private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<Option<StructuredData>, ::errors::DnsError> {
+    debug!("Handing listing of {:?} over to new owners ...", long_name);
+    update_listing(dns_operations, long_name, owners, private_signing_key, |content| (content, new_owners.clone()))
+}
+
+fn update_listing<F>(dns_operations     : &DnsOperations,
+                     long_name          : &String,
+                     owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                     private_signing_key: &::sodiumoxide::crypto::sign::SecretKey,
+                     modifier           : F) -> Result<Option<StructuredData>, ::errors::DnsError>
+                     where F: FnOnce(Vec<u8>) -> (Vec<u8>, Vec<::sodiumoxide::crypto::sign::PublicKey>) {
+    let directory_name = match dns_operations.name_directory {
+        Some(ref directory_name) => directory_name,
+        None => return Ok(None),
+    };
+
+    let bucket = get_bucket(long_name);
+    let mut reads = 0;
+    let mut index = 0;
+    let mut prev_struct_data = None;
+    while take_read(&mut reads) {
+        match try!(dns_operations.get_directory_slot(directory_name, &bucket, index)) {
+            Some((struct_data, listed_name)) => if struct_data.get_owner_keys() == owners && listed_name.as_ref() == Some(long_name) {
+                prev_struct_data = Some(struct_data);
+                break
+            },
+            None => break,
+        }
+        index += 1;
+    }
+    let prev_struct_data = match prev_struct_data {
+        Some(struct_data) => struct_data,
+        None => return Ok(None),
+    };
+
+    let (content, new_owners) = modifier(try!(serialise(long_name)));
+    let prev_owners = if new_owners == *owners {
+        prev_struct_data.get_previous_owner_keys().clone()
+    } else {
+        owners.clone()
+    };
+    Ok(Some(try!(super::dry_run::create_tagged_structured_data(dns_operations,
+                                                               NAME_DIRECTORY_TAG,
+                                                               prev_struct_data.get_identifier().clone(),
+                                                               prev_struct_data.get_version() + 1,
+                                                               content,
+                                                               new_owners,
+                                                               prev_owners,
+                                                               private_signing_key,
+                                                               None))))
+}
+
+/// Count one more read against `MAX_DIRECTORY_SEARCH_READS`, `false` if none is left
+fn take_read(reads: &mut u64) -> bool {
+    if *reads == MAX_DIRECTORY_SEARCH_READS {
+        return false
+    }
+    *reads += 1;
+    true
+}
+
+/// Group of the name directory the name is listed in
+fn get_bucket(long_name: &String) -> String {
+    match long_name.chars().next() {
+        Some(c) if BUCKET_CHARS.contains(c) => c.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Identifier of the `index`-th slot of the given group of the name directory
+fn get_directory_slot_identifier(namespace     : &super::Namespace,
+                                 directory_name: &String,
+                                 bucket        : &String,
+                                 index         : u64) -> XorName {
+    namespace.get_identifier(format!("{}\0{}\0{}", directory_name, bucket, index).as_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use maidsafe_utilities::serialisation::serialise;
+    use dns_operations::{DnsOperations, NameFilter};
+    use dns_operations::test_utils;
+
+    #[test]
+    fn list_search_and_unlist_names() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let directory_name = unwrap_result!(::safe_core::utility::generate_random_string(10));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone())).with_name_directory(directory_name.clone());
+
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let suffix = test_utils::generate_random_dns_name();
+        let dns_names: Vec<String> = vec!["apple", "apricot", "banana"].into_iter().map(|label| format!("{}.{}", label, suffix)).collect();
+
+        // Someone else taking the first slot of a group, listing a name they do not own
+        let rival_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let rival_dns_operations = unwrap_result!(DnsOperations::new(rival_client.clone()));
+        let rival_owners = vec![unwrap_result!(unwrap_result!(rival_client.lock()).get_public_signing_key()).clone()];
+        let rival_secret_signing_key = unwrap_result!(unwrap_result!(rival_client.lock()).get_secret_signing_key()).clone();
+        let identifier = super::get_directory_slot_identifier(rival_dns_operations.get_namespace(), &directory_name, &"a".to_string(), 0);
+        let struct_data = unwrap_result!(super::super::dry_run::create_tagged_structured_data(&rival_dns_operations,
+                                                                                              super::NAME_DIRECTORY_TAG,
+                                                                                              identifier,
+                                                                                              0,
+                                                                                              unwrap_result!(serialise(&dns_names[0])),
+                                                                                              rival_owners,
+                                                                                              vec![],
+                                                                                              &rival_secret_signing_key,
+                                                                                              None));
+        unwrap_result!(unwrap_result!(rival_client.lock()).put(Data::Structured(struct_data), None));
+
+        for dns_name in dns_names.iter() {
+            let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                                                 &messaging_keypair.0,
+                                                                                                 &messaging_keypair.1,
+                                                                                                 &vec![],
+                                                                                                 owners.clone(),
+                                                                                                 &secret_signing_key,
+                                                                                                 None));
+            assert_eq!(auxiliary_struct_data.len(), 1);
+            unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+            test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+        }
+
+        let unregistered_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::client::Client::create_unregistered_client())));
+        let unregistered_dns_operations = DnsOperations::new_unregistered(unregistered_client);
+
+        let page = unwrap_result!(unregistered_dns_operations.search_name_directory(&directory_name,
+                                                                                    &NameFilter::Prefix("ap".to_string()),
+                                                                                    0,
+                                                                                    1));
+        assert_eq!(page.names, vec![dns_names[0].clone()]);
+        assert_eq!(page.next_offset, Some(1));
+        assert!(!page.truncated);
+        let page = unwrap_result!(unregistered_dns_operations.search_name_directory(&directory_name,
+                                                                                    &NameFilter::Prefix("ap".to_string()),
+                                                                                    1,
+                                                                                    1));
+        assert_eq!(page.names, vec![dns_names[1].clone()]);
+        assert_eq!(page.next_offset, None);
+
+        let page = unwrap_result!(unregistered_dns_operations.search_name_directory(&directory_name,
+                                                                                    &NameFilter::Substring("nan".to_string()),
+                                                                                    0,
+                                                                                    10));
+        assert_eq!(page.names, vec![dns_names[2].clone()]);
+
+        match unregistered_dns_operations.search_name_directory(&directory_name, &NameFilter::All, 0, 0) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::InvalidPageSize) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+
+        // Deleting a name removes it from the directory, and a new name reuses its slot
+        let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.delete_dns(&dns_names[0], &secret_signing_key));
+        unwrap_result!(unwrap_result!(client.lock()).delete(Data::Structured(struct_data), None));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+
+        let page = unwrap_result!(unregistered_dns_operations.search_name_directory(&directory_name, &NameFilter::All, 0, 10));
+        assert_eq!(page.names, dns_names[1..].to_vec());
+
+        let dns_name = format!("avocado.{}", suffix);
+        let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                                             &messaging_keypair.0,
+                                                                                             &messaging_keypair.1,
+                                                                                             &vec![],
+                                                                                             owners,
+                                                                                             &secret_signing_key,
+                                                                                             None));
+        assert_eq!(auxiliary_struct_data[0].get_version(), 2);
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+
+        let page = unwrap_result!(unregistered_dns_operations.search_name_directory(&directory_name,
+                                                                                    &NameFilter::Prefix("a".to_string()),
+                                                                                    0,
+                                                                                    10));
+        assert_eq!(page.names, vec![dns_names[1].clone(), dns_name]);
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+use xor_name::XorName;
+
+/// Prefix of the input hashed by `DomainSeparatedIdentifierScheme`
+const DOMAIN_SEPARATION_TAG: &'static [u8] = b"safe_dns/identifier/v1";
+
+/// Way the identifiers of the structured data used by the library are derived, eg., that of the
+/// Dns record of a name from the name. See `DnsOperations::migrate_dns` for moving names
+/// registered under one scheme to another.
+pub trait IdentifierScheme: Send + Sync {
+    /// Identifier within the given namespace (see `Namespace::get_name`) of the structured data
+    /// derived from `input`
+    fn get_identifier(&self, namespace: &String, input: &[u8]) -> XorName;
+}
+
+/// Scheme all names were registered under before schemes could be chosen: `sha512(input)` in the
+/// default namespace, prefixed by the hash of the namespace name in others.
+pub struct LegacyIdentifierScheme;
+
+impl IdentifierScheme for LegacyIdentifierScheme {
+    fn get_identifier(&self, namespace: &String, input: &[u8]) -> XorName {
+        if namespace.is_empty() {
+            return XorName::new(::sodiumoxide::crypto::hash::sha512::hash(input).0)
+        }
+
+        // Prefixing the fixed size hash of the name keeps inputs of different namespaces apart
+        let mut namespaced_input = ::sodiumoxide::crypto::hash::sha512::hash(namespace.as_bytes()).0.to_vec();
+        namespaced_input.extend(input.iter().cloned());
+        XorName::new(::sodiumoxide::crypto::hash::sha512::hash(&namespaced_input).0)
+    }
+}
+
+/// Scheme hashing a tag specific to this library and the namespace name along with the input, so
+/// identifiers can not coincide with those of other applications hashing the same input, nor
+/// across namespaces, the default one included.
+pub struct DomainSeparatedIdentifierScheme;
+
+impl IdentifierScheme for DomainSeparatedIdentifierScheme {
+    fn get_identifier(&self, namespace: &String, input: &[u8]) -> XorName {
+        let mut separated_input = DOMAIN_SEPARATION_TAG.to_vec();
+        separated_input.extend(::sodiumoxide::crypto::hash::sha512::hash(namespace.as_bytes()).0.iter().cloned());
+        separated_input.extend(input.iter().cloned());
+        XorName::new(::sodiumoxide::crypto::hash::sha512::hash(&separated_input).0)
+    }
+}
+
+/// Registry the Dns-names of a `DnsOperations` instance live in. Names registered in one
+/// namespace are neither visible from nor in conflict with those of another, so eg., test,
+/// staging and production registries can run side by side on the same network.
+/// The default namespace is the one all names were registered in before namespaces existed.
+#[derive(Clone)]
+pub struct Namespace {
+    name  : String,
+    tag   : u64,
+    scheme: ::std::sync::Arc<IdentifierScheme>,
+}
+
+impl Namespace {
+    /// Namespace of the given name, housing its Dns records in structured data of the given tag.
+    /// Structured data of other kinds (eg., commitments or linked services) keep their tags but
+    /// are identified within the namespace too, so the tag can not be one of theirs
+    /// (`ReservedNamespaceTag`). The name must not be empty, which is left to the default
+    /// namespace (`InvalidNamespaceName`). Identifiers are derived through the
+    /// `LegacyIdentifierScheme`, see `with_identifier_scheme`.
+    pub fn new(name: String, tag: u64) -> Result<Namespace, ::errors::DnsError> {
+        if name.is_empty() {
+            return Err(::errors::DnsError::InvalidNamespaceName)
+        }
+        if get_reserved_tags().contains(&tag) {
+            return Err(::errors::DnsError::ReservedNamespaceTag)
+        }
+
+        Ok(Namespace {
+            name  : name,
+            tag   : tag,
+            scheme: ::std::sync::Arc::new(LegacyIdentifierScheme),
+        })
+    }
+
+    /// Same namespace, deriving identifiers through the given scheme. The names registered by the
+    /// user are still kept track of in the same dns configuration, so that they can be moved over
+    /// through `DnsOperations::migrate_dns`.
+    pub fn with_identifier_scheme(mut self, scheme: ::std::sync::Arc<IdentifierScheme>) -> Namespace {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Name of the namespace, empty for the default one
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Tag of the structured data housing the Dns records of the namespace
+    pub fn get_tag(&self) -> u64 {
+        self.tag
+    }
+
+    /// Scheme the identifiers of the namespace are derived through
+    pub fn get_identifier_scheme(&self) -> &::std::sync::Arc<IdentifierScheme> {
+        &self.scheme
+    }
+
+    /// Whether this is the default namespace
+    pub fn is_default(&self) -> bool {
+        self.name.is_empty()
+    }
+
+    /// Identifier within this namespace of the structured data derived from `input`, eg., the Dns
+    /// record of a name is identified by the name.
+    pub fn get_identifier(&self, input: &[u8]) -> XorName {
+        self.scheme.get_identifier(&self.name, input)
+    }
+}
+
+impl Default for Namespace {
+    /// The namespace of the names registered before namespaces existed, whose dns configuration
+    /// is kept in the file it always was
+    fn default() -> Namespace {
+        Namespace {
+            name  : String::new(),
+            tag   : super::DNS_TAG,
+            scheme: ::std::sync::Arc::new(LegacyIdentifierScheme),
+        }
+    }
+}
+
+/// Tags of the structured data other than Dns records, identified within the namespace
+fn get_reserved_tags() -> [u64; 4] {
+    [super::linked_service::LINKED_SERVICE_TAG,
+     super::commitment::COMMITMENT_TAG,
+     super::owner_index::OWNER_INDEX_TAG,
+     super::name_directory::NAME_DIRECTORY_TAG]
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use xor_name::XorName;
+    use dns_operations::{DnsOperations, Namespace};
+    use dns_operations::test_utils;
+
+    #[test]
+    fn namespaces_are_isolated() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let staging = unwrap_result!(Namespace::new("staging".to_string(), 15001));
+        let production_dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+        let staging_dns_operations = unwrap_result!(DnsOperations::new_in_namespace(client.clone(), staging.clone()));
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let production_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([1; 64]),
+                                                                                        15000,
+                                                                                        false,
+                                                                                        ::safe_nfs::AccessLevel::Public);
+        let staging_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([2; 64]),
+                                                                                     15000,
+                                                                                     false,
+                                                                                     ::safe_nfs::AccessLevel::Public);
+
+        // The same name can be registered in each namespace
+        let (struct_data, _) = unwrap_result!(production_dns_operations.register_dns(dns_name.clone(),
+                                                                                   &messaging_keypair.0,
+                                                                                   &messaging_keypair.1,
+                                                                                   &vec![("www".to_string(), production_dir_key.clone())],
+                                                                                   owners.clone(),
+                                                                                   &secret_signing_key,
+                                                                                   None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        assert!(!unwrap_result!(staging_dns_operations.get_all_registered_names()).contains(&dns_name));
+
+        let (struct_data, _) = unwrap_result!(staging_dns_operations.register_dns(dns_name.clone(),
+                                                                                &messaging_keypair.0,
+                                                                                &messaging_keypair.1,
+                                                                                &vec![("www".to_string(), staging_dir_key.clone())],
+                                                                                owners,
+                                                                                &secret_signing_key,
+                                                                                None));
+        assert_eq!(struct_data.get_type_tag(), staging.get_tag());
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        let unregistered_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::client::Client::create_unregistered_client())));
+        let unregistered_dns_operations = DnsOperations::new_unregistered_in_namespace(unregistered_client, staging);
+        assert_eq!(unwrap_result!(unregistered_dns_operations.get_service_home_directory_key(&dns_name, &"www".to_string(), None)),
+                   staging_dir_key);
+        assert_eq!(unwrap_result!(production_dns_operations.get_service_home_directory_key(&dns_name, &"www".to_string(), None)),
+                   production_dir_key);
+    }
+
+    #[test]
+    fn invalid_namespaces_are_rejected() {
+        for tag in super::get_reserved_tags().iter() {
+            match Namespace::new("reserved".to_string(), *tag) {
+                Ok(_) => panic!("Should have been an error"),
+                Err(::errors::DnsError::ReservedNamespaceTag) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+        assert!(Namespace::new("reserved".to_string(), Namespace::default().get_tag()).is_ok());
+
+        match Namespace::new(String::new(), 15002) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::InvalidNamespaceName) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use std::collections::BTreeMap;
+use routing::{ImmutableData, ImmutableDataType};
+use xor_name::XorName;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use super::{DataEncryptionKeys, DnsOperations};
+use super::dns_record::{Dns, Service};
+use super::validation::MAX_DNS_RECORD_SIZE;
+
+/// Maximum serialised size of the services held by a single overflow chunk
+pub const MAX_SERVICE_CHUNK_SIZE: usize = MAX_DNS_RECORD_SIZE;
+
+/// Maximum number of overflow chunks a Dns record can link to
+pub const MAX_SERVICE_CHUNKS: usize = 64;
+
+/// Part of the services of a Dns record, stored as immutable data
+#[derive(RustcEncodable, RustcDecodable)]
+struct ServiceChunk {
+    services: BTreeMap<String, Service>,
+}
+
+/// If the Dns record is too large for a structured data, get a copy of it with its services
+/// moved into immutable data chunks (see `Dns::services_overflow`). The chunks are stored right
+/// away, except for those already listed in `prev_services_overflow` (the chunks of the record
+/// being replaced).
+/// Each service goes to the chunk picked by the hash of its name and chunks are sealed with a
+/// nonce derived from their content, so a chunk whose services did not change keeps its name and
+/// only the changed chunks are stored again.
+pub fn spill_services(dns_operations        : &DnsOperations,
+                      dns_record            : &Dns,
+                      prev_services_overflow: &Vec<XorName>,
+                      data_encryption_keys  : DataEncryptionKeys) -> Result<Dns, ::errors::DnsError> {
+    let mut spilled_dns_record = dns_record.clone();
+    let size = try!(dns_record.get_serialised_size());
+    if size <= MAX_DNS_RECORD_SIZE {
+        return Ok(spilled_dns_record)
+    }
+
+    let mut service_sizes = Vec::with_capacity(dns_record.services.len());
+    for (service_name, service) in dns_record.services.iter() {
+        let service_size = try!(serialise(&(service_name, service))).len();
+        if service_size > MAX_SERVICE_CHUNK_SIZE {
+            return Err(::errors::DnsError::DnsRecordTooLarge {
+                limit: MAX_DNS_RECORD_SIZE,
+                size : size,
+            })
+        }
+        service_sizes.push((service_name, service_size));
+    }
+
+    // Keep the number of chunks of the record being replaced as long as the services fit, so
+    // that services stay in the chunks they were in
+    let mut chunk_count = ::std::cmp::max(prev_services_overflow.len(), 1);
+    loop {
+        if chunk_count > MAX_SERVICE_CHUNKS {
+            return Err(::errors::DnsError::DnsRecordTooLarge {
+                limit: MAX_DNS_RECORD_SIZE,
+                size : size,
+            })
+        }
+        let mut chunk_sizes = vec![0; chunk_count];
+        for &(service_name, service_size) in service_sizes.iter() {
+            chunk_sizes[get_chunk_index(service_name, chunk_count)] += service_size;
+        }
+        if chunk_sizes.iter().all(|chunk_size| *chunk_size <= MAX_SERVICE_CHUNK_SIZE) {
+            break
+        }
+        chunk_count *= 2;
+    }
+
+    let mut chunks: Vec<ServiceChunk> = (0..chunk_count).map(|_| ServiceChunk { services: BTreeMap::new() }).collect();
+    for (service_name, service) in dns_record.services.iter() {
+        let _ = chunks[get_chunk_index(service_name, chunk_count)].services.insert(service_name.clone(), service.clone());
+    }
+
+    debug!("Spilling services into {:?} chunks ...", chunk_count);
+    let mut chunk_names = Vec::with_capacity(chunk_count);
+    for chunk in chunks.iter() {
+        let sealed_chunk = try!(seal_chunk(try!(serialise(chunk)), data_encryption_keys));
+        let chunk_name = ImmutableData::new(ImmutableDataType::Normal, sealed_chunk.clone()).name();
+        if !prev_services_overflow.contains(&chunk_name) && !chunk_names.contains(&chunk_name) {
+            let _ = try!(dns_operations.put_immutable_data(sealed_chunk));
+        }
+        chunk_names.push(chunk_name);
+    }
+
+    spilled_dns_record.services = ::std::collections::HashMap::new();
+    spilled_dns_record.services_overflow = chunk_names;
+    Ok(spilled_dns_record)
+}
+
+/// Move the services held by the overflow chunks of the Dns record back into it
+pub fn load_spilled_services(dns_operations      : &DnsOperations,
+                             dns_record          : &mut Dns,
+                             data_decryption_keys: DataEncryptionKeys) -> Result<(), ::errors::DnsError> {
+    if dns_record.services_overflow.len() > MAX_SERVICE_CHUNKS {
+        return Err(::errors::DnsError::from("Too many chunks of overflowed services"))
+    }
+
+    for chunk_name in ::std::mem::replace(&mut dns_record.services_overflow, Vec::new()) {
+        debug!("Loading overflowed services from chunk {:?} ...", chunk_name);
+        let chunk: ServiceChunk = try!(deserialise(&try!(dns_operations.get_decrypted_immutable_data(chunk_name, data_decryption_keys))));
+        dns_record.services.extend(chunk.services.into_iter());
+    }
+
+    Ok(())
+}
+
+/// Chunk the given service goes to, out of `chunk_count`. As the hash is reduced modulo the
+/// count, doubling the count splits every chunk in two.
+fn get_chunk_index(service_name: &String, chunk_count: usize) -> usize {
+    let digest = ::sodiumoxide::crypto::hash::sha256::hash(service_name.as_bytes());
+    let hash = digest.0.iter().take(8).fold(0u64, |hash, byte| (hash << 8) | *byte as u64);
+    (hash % chunk_count as u64) as usize
+}
+
+/// Chunk content as stored, sealed like `DnsOperations::put_encrypted_immutable_data` does, but
+/// with a nonce derived from the content so that equal chunks are stored under the same name
+fn seal_chunk(content             : Vec<u8>,
+              data_encryption_keys: DataEncryptionKeys) -> Result<Vec<u8>, ::errors::DnsError> {
+    match data_encryption_keys {
+        Some((public_key, secret_key, _)) => {
+            let digest = ::sodiumoxide::crypto::hash::sha256::hash(&content);
+            let nonce = try!(::sodiumoxide::crypto::box_::Nonce::from_slice(&digest.0[..::sodiumoxide::crypto::box_::NONCEBYTES])
+                                 .ok_or(::errors::DnsError::from("Programming Error - Investigate !!")));
+            let mut sealed_content = nonce.0.to_vec();
+            sealed_content.extend(::sodiumoxide::crypto::box_::seal(&content, &nonce, public_key, secret_key));
+            Ok(sealed_content)
+        },
+        None => Ok(content),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use dns_operations::{Dns, DnsOperations, Service, ServiceTarget};
+    use dns_operations::test_utils;
+
+    #[test]
+    fn spill_and_reassemble_services() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let (dns_name, keys) = test_utils::register_test_name(&dns_operations);
+        let secret_signing_key = keys.secret_signing_key;
+
+        // Each redirect is about 1 KiB, so together these exceed a single structured data
+        let padding = ::std::iter::repeat('a').take(1000).collect::<String>();
+        let service_count = 150;
+
+        for index in 0..service_count {
+            let target = ServiceTarget::Redirect(format!("safe:{}{}.com", padding, index));
+            let struct_data = unwrap_result!(dns_operations.add_service_target(&dns_name,
+                                                                             (format!("customer-{}", index), target),
+                                                                             &secret_signing_key,
+                                                                             None));
+            unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        }
+
+        assert_eq!(unwrap_result!(dns_operations.get_all_services(&dns_name, None)).len(), service_count);
+        assert_eq!(unwrap_result!(dns_operations.get_service_target(&dns_name, &"customer-7".to_string(), None)),
+                   ServiceTarget::Redirect(format!("safe:{}7.com", padding)));
+
+        let resolution = unwrap_result!(dns_operations.resolve_name(&format!("customer-42.{}", dns_name), None));
+        assert_eq!(unwrap_result!(dns_operations.get_resolved_service_target(&resolution, resolution.get_service_name(), None)),
+                   ServiceTarget::Redirect(format!("safe:{}42.com", padding)));
+    }
+
+    #[test]
+    fn spill_keeps_unchanged_chunks() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client)).dry_run();
+        let encryption_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let nonce = ::sodiumoxide::crypto::box_::gen_nonce();
+        let keys = Some((&encryption_keypair.0, &encryption_keypair.1, &nonce));
+
+        let padding = ::std::iter::repeat('a').take(1000).collect::<String>();
+        let mut dns_record = Dns {
+            long_name        : test_utils::generate_random_dns_name(),
+            services         : ::std::collections::HashMap::new(),
+            encryption_key   : encryption_keypair.0,
+            delegations      : ::std::collections::HashMap::new(),
+            alias            : None,
+            text_records     : ::std::collections::HashMap::new(),
+            default_service  : None,
+            wildcard_service : None,
+            history          : None,
+            services_overflow: Vec::new(),
+        };
+        for index in 0..400 {
+            let target = ServiceTarget::Redirect(format!("safe:{}{}.com", padding, index));
+            let _ = dns_record.services.insert(format!("customer-{}", index), Service::new(target));
+        }
+
+        let spilled = unwrap_result!(super::spill_services(&dns_operations, &dns_record, &vec![], keys));
+        assert!(spilled.services.is_empty());
+        assert!(spilled.services_overflow.len() > 1);
+        assert_eq!(unwrap_result!(super::spill_services(&dns_operations, &dns_record, &spilled.services_overflow, keys)),
+                   spilled);
+
+        // Changing a single service only changes the chunk holding it
+        let target = ServiceTarget::Redirect(format!("safe:{}changed.com", padding));
+        let _ = dns_record.services.insert("customer-7".to_string(), Service::new(target));
+        let respilled = unwrap_result!(super::spill_services(&dns_operations, &dns_record, &spilled.services_overflow, keys));
+        assert_eq!(respilled.services_overflow.len(), spilled.services_overflow.len());
+        assert_eq!(respilled.services_overflow
+                            .iter()
+                            .zip(spilled.services_overflow.iter())
+                            .filter(|&(respilled_chunk, spilled_chunk)| respilled_chunk != spilled_chunk)
+                            .count(),
+                   1);
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use xor_name::XorName;
+use maidsafe_utilities::serialisation::{serialise, deserialise};
+use super::DnsOperations;
+
+/// Tag of the structured data listing the Dns-names an owner key owns
+pub const OWNER_INDEX_TAG: u64 = 8;
+
+/// Maximum serialised size of the index of the names owned by a key
+pub const MAX_OWNER_INDEX_SIZE: usize = super::validation::MAX_DNS_RECORD_SIZE;
+
+impl DnsOperations {
+    /// Get an instance keeping the Dns-names in the public index of the owner signing their
+    /// registration, deletion or transfer, see `names_owned_by`. The index is returned by those
+    /// operations along with the Dns record, to be stored by the caller.
+    pub fn with_owner_index(mut self) -> DnsOperations {
+        self.owner_index = true;
+        self
+    }
+
+    /// Add the Dns-name to the public index of names owned by the given key. Names are indexed
+    /// on their own by instances keeping owner indexes for the owner signing their registration;
+    /// this is eg., for the other owners, or for the new owners of a transferred name. The
+    /// returned structured data is to be put (if its version is 0) or posted by the caller. The
+    /// index is signed by the owner it lists names for.
+    pub fn index_owned_name(&self,
+                            long_name          : &String,
+                            owner              : &::sodiumoxide::crypto::sign::PublicKey,
+                            private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<StructuredData, ::errors::DnsError> {
+        self.update_owner_index(owner, private_signing_key, |names| {
+            if !names.contains(long_name) {
+                debug!("Adding {:?} to owner index ...", long_name);
+                names.push(long_name.clone());
+                names.sort();
+            }
+        })
+    }
+
+    /// Remove the Dns-name from the public index of names owned by the given key, the counterpart
+    /// of `index_owned_name`.
+    pub fn unindex_owned_name(&self,
+                              long_name          : &String,
+                              owner              : &::sodiumoxide::crypto::sign::PublicKey,
+                              private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<StructuredData, ::errors::DnsError> {
+        self.update_owner_index(owner, private_signing_key, |names| {
+            debug!("Removing {:?} from owner index ...", long_name);
+            names.retain(|name| name != long_name);
+        })
+    }
+
+    /// Get the Dns-names owned by the given key according to its public index. Only names whose
+    /// housing structured data currently lists the key among its owners are returned, so stale or
+    /// false entries of the index are skipped. Usable by unregistered clients too.
+    pub fn names_owned_by(&self, owner: &::sodiumoxide::crypto::sign::PublicKey) -> Result<Vec<String>, ::errors::DnsError> {
+        match try!(self.get_owner_index(owner)) {
+            Some((_, names)) => self.retain_owned_names(owner, names),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Structured data of the index with the given change applied, dropping the names the owner
+    /// no longer owns on the way
+    fn update_owner_index<F>(&self,
+                             owner              : &::sodiumoxide::crypto::sign::PublicKey,
+                             private_signing_key: &::sodiumoxide::crypto::sign::SecretKey,
+                             modifier           : F) -> Result<StructuredData, ::errors::DnsError>
+                             where F: FnOnce(&mut Vec<String>) {
+        let (version, mut names) = match try!(self.get_owner_index(owner)) {
+            Some((struct_data, names)) => (struct_data.get_version() + 1, try!(self.retain_owned_names(owner, names))),
+            None => (0, Vec::new()),
+        };
+        modifier(&mut names);
+
+        let encoded_names = try!(serialise(&names));
+        if encoded_names.len() > MAX_OWNER_INDEX_SIZE {
+            return Err(::errors::DnsError::OwnerIndexTooLarge)
+        }
+
+        super::dry_run::create_tagged_structured_data(self,
+                                                      OWNER_INDEX_TAG,
+                                                      get_owner_index_identifier(&self.namespace, owner),
+                                                      version,
+                                                      encoded_names,
+                                                      vec![owner.clone()],
+                                                      vec![],
+                                                      private_signing_key,
+                                                      None)
+    }
+
+    fn retain_owned_names(&self,
+                          owner: &::sodiumoxide::crypto::sign::PublicKey,
+                          names: Vec<String>) -> Result<Vec<String>, ::errors::DnsError> {
+        let mut owned_names = Vec::with_capacity(names.len());
+        for name in names.into_iter() {
+            match self.get_housing_structured_data(&name) {
+                Ok(struct_data) => if !struct_data.get_data().is_empty() && struct_data.get_owner_keys().contains(owner) {
+                    owned_names.push(name);
+                },
+                Err(ref error) if super::storage::is_data_not_found(error) => (),
+                Err(::errors::DnsError::DnsRecordNotFound) => (),
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(owned_names)
+    }
+
+    fn get_owner_index(&self,
+                       owner: &::sodiumoxide::crypto::sign::PublicKey) -> Result<Option<(StructuredData, Vec<String>)>, ::errors::DnsError> {
+        let struct_data = match self.get_tagged_structured_data(get_owner_index_identifier(&self.namespace, owner), OWNER_INDEX_TAG) {
+            Ok(struct_data) => struct_data,
+            Err(ref error) if super::storage::is_data_not_found(error) => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        if *struct_data.get_owner_keys() != vec![owner.clone()] {
+            return Err(::errors::DnsError::from("Owner index is not owned by the owner it lists names for"))
+        }
+
+        let names = try!(deserialise(&try!(self.storage.get_structured_data_content(&struct_data, None))));
+        Ok(Some((struct_data, names)))
+    }
+}
+
+/// Index of the owner the private key signs for with the Dns-name added, if the instance keeps
+/// owner indexes
+pub fn index_signed_name(dns_operations     : &DnsOperations,
+                         long_name          : &String,
+                         owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                         private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<Option<StructuredData>, ::errors::DnsError> {
+    if !dns_operations.owner_index {
+        return Ok(None)
+    }
+
+    match get_signing_owner(owners, private_signing_key) {
+        Some(owner) => Ok(Some(try!(dns_operations.index_owned_name(long_name, &owner, private_signing_key)))),
+        None => Ok(None),
+    }
+}
+
+/// Index of the owner the private key signs for with the Dns-name removed, the counterpart of
+/// `index_signed_name`
+pub fn unindex_signed_name(dns_operations     : &DnsOperations,
+                           long_name          : &String,
+                           owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                           private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Result<Option<StructuredData>, ::errors::DnsError> {
+    if !dns_operations.owner_index {
+        return Ok(None)
+    }
+
+    match get_signing_owner(owners, private_signing_key) {
+        Some(owner) => Ok(Some(try!(dns_operations.unindex_owned_name(long_name, &owner, private_signing_key)))),
+        None => Ok(None),
+    }
+}
+
+/// Owner among the given ones the private key signs for
+pub fn get_signing_owner(owners             : &Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                         private_signing_key: &::sodiumoxide::crypto::sign::SecretKey) -> Option<::sodiumoxide::crypto::sign::PublicKey> {
+    // An ed25519 secret key ends with its public key
+    let public_key = &private_signing_key.0[::sodiumoxide::crypto::sign::SECRETKEYBYTES - ::sodiumoxide::crypto::sign::PUBLICKEYBYTES..];
+    owners.iter().find(|owner| owner.0[..] == *public_key).cloned()
+}
+
+/// Identifier of the structured data listing the Dns-names owned by the given key
+fn get_owner_index_identifier(namespace: &super::Namespace, owner: &::sodiumoxide::crypto::sign::PublicKey) -> XorName {
+    namespace.get_identifier(&owner.0)
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use dns_operations::DnsOperations;
+    use dns_operations::test_utils;
+
+    #[test]
+    fn find_names_by_owner() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client));
+        let (dns_name, keys) = test_utils::register_test_name(&dns_operations);
+        let owner = keys.owners[0].clone();
+
+        let struct_data = unwrap_result!(dns_operations.index_owned_name(&dns_name, &owner, &keys.secret_signing_key));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, vec![struct_data]);
+
+        let unregistered_client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::client::Client::create_unregistered_client())));
+        let unregistered_dns_operations = DnsOperations::new_unregistered(unregistered_client);
+        assert_eq!(unwrap_result!(unregistered_dns_operations.names_owned_by(&owner)), vec![dns_name.clone()]);
+
+        let struct_data = unwrap_result!(dns_operations.unindex_owned_name(&dns_name, &owner, &keys.secret_signing_key));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, vec![struct_data]);
+        assert!(unwrap_result!(unregistered_dns_operations.names_owned_by(&owner)).is_empty());
+    }
+
+    #[test]
+    fn owner_index_follows_registration_and_transfer() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone())).with_owner_index();
+        let (dns_name, keys) = test_utils::register_test_name(&dns_operations);
+        let owner = keys.owners[0].clone();
+        assert_eq!(unwrap_result!(dns_operations.names_owned_by(&owner)), vec![dns_name.clone()]);
+
+        // Handing the name over drops it from the index of the previous owner
+        let new_owner_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+        let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.transfer_dns(&dns_name,
+                                                                                             vec![new_owner_keypair.0.clone()],
+                                                                                             &keys.secret_signing_key,
+                                                                                             None));
+        assert_eq!(auxiliary_struct_data.len(), 1);
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+        assert!(unwrap_result!(dns_operations.names_owned_by(&owner)).is_empty());
+        assert!(!unwrap_result!(dns_operations.get_all_registered_names()).contains(&dns_name));
+
+        let struct_data = unwrap_result!(dns_operations.index_owned_name(&dns_name, &new_owner_keypair.0, &new_owner_keypair.1));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, vec![struct_data]);
+        assert_eq!(unwrap_result!(dns_operations.names_owned_by(&new_owner_keypair.0)), vec![dns_name]);
+
+        // Deleting a name drops it from the index too
+        let (other_dns_name, _) = test_utils::register_test_name(&dns_operations);
+        assert_eq!(unwrap_result!(dns_operations.names_owned_by(&owner)), vec![other_dns_name.clone()]);
+
+        let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.delete_dns(&other_dns_name, &keys.secret_signing_key));
+        unwrap_result!(unwrap_result!(client.lock()).delete(Data::Structured(struct_data), None));
+        test_utils::publish_auxiliary_structured_data(&dns_operations, auxiliary_struct_data);
+        assert!(unwrap_result!(dns_operations.names_owned_by(&owner)).is_empty());
+    }
+}
@@ -0,0 +1,492 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use xor_name::XorName;
+use routing::StructuredData;
+use super::{DataEncryptionKeys, DnsOperations};
+use super::validation;
+use super::dns_record::ServiceTarget;
+use super::DEFAULT_SERVICE_NAME;
+
+/// Minimum number of labels a registered Dns-name must have to be considered during resolution.
+/// This prevents a registration of a bare top level label (eg., `com`) from capturing every name
+/// under it.
+pub const MIN_REGISTERED_NAME_LABELS: usize = 2;
+
+/// Maximum number of delegations followed while resolving a single name
+pub const MAX_DELEGATION_DEPTH: usize = 8;
+
+/// Maximum number of aliases followed while resolving a single name
+pub const MAX_ALIAS_DEPTH: usize = 8;
+
+/// Outcome of resolving a fully qualified name, eg., docs.api.example.com
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Resolution {
+    /// Longest suffix of the resolved name which is a registered Dns-name
+    pub long_name      : String,
+    /// Label preceding the registered Dns-name and any delegated labels, if any. This is the
+    /// service to be looked up.
+    pub service_name   : Option<String>,
+    /// Service to be looked up if `service_name` is not set - the default service of the record,
+    /// or `DEFAULT_SERVICE_NAME` if it declares none
+    pub default_service: String,
+    /// Set to the service looked up if it is not an explicit service of the record and will
+    /// hence be answered by the wildcard service
+    pub wildcard_label : Option<String>,
+    /// Delegated labels followed from `long_name`, outermost first
+    pub delegations    : Vec<String>,
+    /// Chain of names traversed through aliases, starting with the requested name and ending with
+    /// the name finally resolved. Empty if no alias was followed.
+    pub aliases        : Vec<String>,
+    /// Identifier of the structured data housing the Dns record that answers for the service
+    pub identifier     : XorName,
+}
+
+impl Resolution {
+    /// Service to be looked up - `service_name` or, if there is no such label, `default_service`
+    pub fn get_service_name(&self) -> &String {
+        self.service_name.as_ref().unwrap_or(&self.default_service)
+    }
+}
+
+/// Order in which the mirrors of a service are tried
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MirrorSelection {
+    /// Primary target first, then the mirrors in the order they were added
+    PrimaryThenFallback,
+    /// All targets in random order, spreading the load across mirrors
+    Random,
+}
+
+/// Content a resolved service points to, fetched from the network
+pub enum ServiceHandle {
+    /// Listing of the home directory of the service
+    Directory(::safe_nfs::directory_listing::DirectoryListing),
+    /// A single file, readable through `safe_nfs::helper::file_helper::FileHelper`
+    File(::safe_nfs::file::File),
+    /// Content-addressed blob
+    ImmutableData(::routing::ImmutableData),
+    /// Redirect to another SAFE url which the caller is expected to act upon
+    Redirect(String),
+}
+
+enum ResolutionStep {
+    Resolved(Resolution),
+    Aliased(String),
+}
+
+impl DnsOperations {
+    /// Resolve a fully qualified name into the Dns record and the service it refers to.
+    ///
+    /// The name is split into labels and suffixes of it are tried, longest first, until one is
+    /// found to be a registered Dns-name. Suffixes with less than `MIN_REGISTERED_NAME_LABELS`
+    /// labels are never tried. The labels preceding the registered suffix are then consumed from
+    /// the right:
+    /// - while the current record delegates the rightmost label, the delegation is followed to
+    ///   the delegated record (at most `MAX_DELEGATION_DEPTH` times, never visiting a record twice)
+    /// - no labels left  => no service was specified, the default service of the record (or
+    ///                      `DEFAULT_SERVICE_NAME` if it declares none) is to be looked up
+    /// - one label left  => that label is the service
+    /// - more labels     => unresolvable, as service names can not contain a '.'
+    ///
+    /// If any record reached this way is an alias, the labels not yet consumed are put in front of
+    /// the alias target and resolution starts afresh with that name (at most `MAX_ALIAS_DEPTH`
+    /// times, never resolving a name twice).
+    ///
+    /// eg., for `docs.api.example.com` the candidates are `docs.api.example.com`,
+    /// `api.example.com` and `example.com` in that order. If `example.com` is the first one
+    /// registered and it delegates `api` to another record, the resolution is service `docs` of
+    /// that delegated record.
+    pub fn resolve_name(&self,
+                        name                : &String,
+                        data_decryption_keys: DataEncryptionKeys) -> Result<Resolution, ::errors::DnsError> {
+        let mut aliases: Vec<String> = Vec::new();
+        let mut current_name = name.clone();
+
+        loop {
+            // Aliased records can belong to someone else, hence are expected to be public
+            let keys = if aliases.is_empty() { data_decryption_keys } else { None };
+            match try!(self.resolve_name_without_aliases(&current_name, keys)) {
+                ResolutionStep::Resolved(mut resolution) => {
+                    if !aliases.is_empty() {
+                        aliases.push(current_name);
+                    }
+                    resolution.aliases = aliases;
+                    return Ok(resolution)
+                },
+                ResolutionStep::Aliased(next_name) => {
+                    if aliases.len() == MAX_ALIAS_DEPTH {
+                        return Err(::errors::DnsError::AliasDepthExceeded)
+                    }
+                    if next_name == current_name || aliases.contains(&next_name) {
+                        return Err(::errors::DnsError::AliasLoop)
+                    }
+
+                    debug!("Following alias {:?} -> {:?} ...", current_name, next_name);
+                    aliases.push(current_name);
+                    current_name = next_name;
+                },
+            }
+        }
+    }
+
+    /// Get the home directory for the given service of the Dns record a name was resolved to.
+    pub fn get_resolved_service_home_directory_key(&self,
+                                                   resolution          : &Resolution,
+                                                   service_name        : &String,
+                                                   data_decryption_keys: DataEncryptionKeys) -> Result<::safe_nfs::metadata::directory_key::DirectoryKey, ::errors::DnsError> {
+        match try!(self.get_resolved_service_target(resolution, service_name, data_decryption_keys)) {
+            ServiceTarget::Directory(directory_key) => Ok(directory_key),
+            _ => Err(::errors::DnsError::ServiceIsNotADirectory),
+        }
+    }
+
+    /// Get what the given service of the Dns record a name was resolved to points to - its
+    /// primary target. Redirects are returned as such so that browsers and gateways can issue a
+    /// permanent redirect.
+    pub fn get_resolved_service_target(&self,
+                                       resolution          : &Resolution,
+                                       service_name        : &String,
+                                       data_decryption_keys: DataEncryptionKeys) -> Result<ServiceTarget, ::errors::DnsError> {
+        let targets = try!(self.get_resolved_service_targets(resolution, service_name, MirrorSelection::PrimaryThenFallback, data_decryption_keys));
+        targets.into_iter().next().ok_or(::errors::DnsError::ServiceTargetNotFound)
+    }
+
+    /// Get all the mirrors of the given service of the Dns record a name was resolved to, in the
+    /// order they should be tried according to `selection`.
+    pub fn get_resolved_service_targets(&self,
+                                        resolution          : &Resolution,
+                                        service_name        : &String,
+                                        selection           : MirrorSelection,
+                                        data_decryption_keys: DataEncryptionKeys) -> Result<Vec<ServiceTarget>, ::errors::DnsError> {
+        // Delegated or aliased records can belong to someone else, hence are expected to be public
+        let keys = if resolution.delegations.is_empty() && resolution.aliases.is_empty() {
+            data_decryption_keys
+        } else {
+            None
+        };
+        let (_, dns_record) = try!(self.get_structured_data_and_dns_record(resolution.identifier.clone(), keys));
+        if dns_record.is_expired() {
+            return Err(::errors::DnsError::DnsRecordExpired)
+        }
+        let mut targets = try!(super::linked_service::find_service(self, &dns_record, service_name)).0.targets;
+        if selection == MirrorSelection::Random {
+            shuffle(&mut targets);
+        }
+        Ok(targets)
+    }
+
+    /// Fetch what the given service of the Dns record a name was resolved to points to, in the
+    /// form appropriate for the kind of target. Mirrors are tried in the order given by
+    /// `selection` until one of them can be fetched.
+    pub fn get_resolved_service_handle(&self,
+                                       resolution          : &Resolution,
+                                       service_name        : &String,
+                                       selection           : MirrorSelection,
+                                       data_decryption_keys: DataEncryptionKeys) -> Result<ServiceHandle, ::errors::DnsError> {
+        let targets = try!(self.get_resolved_service_targets(resolution, service_name, selection, data_decryption_keys));
+        let mut last_error = ::errors::DnsError::ServiceTargetNotFound;
+        for target in targets.into_iter() {
+            match self.get_service_handle(target) {
+                Ok(handle) => return Ok(handle),
+                Err(error) => {
+                    debug!("Could not fetch service mirror - {:?}. Trying next ...", error);
+                    last_error = error;
+                },
+            }
+        }
+
+        Err(last_error)
+    }
+
+    fn get_service_handle(&self, target: ServiceTarget) -> Result<ServiceHandle, ::errors::DnsError> {
+        match target {
+            ServiceTarget::Directory(directory_key) => Ok(ServiceHandle::Directory(try!(self.storage.get_directory_listing(&directory_key)))),
+            ServiceTarget::File { directory, file_name } => {
+                let dir_listing = try!(self.storage.get_directory_listing(&directory));
+                let file = try!(dir_listing.get_files().iter().find(|file| *file.get_name() == file_name).ok_or(::errors::DnsError::ServiceTargetNotFound));
+                Ok(ServiceHandle::File(file.clone()))
+            },
+            ServiceTarget::ImmutableData(name) => Ok(ServiceHandle::ImmutableData(try!(self.get_immutable_data(name)))),
+            ServiceTarget::Redirect(url) => Ok(ServiceHandle::Redirect(url)),
+        }
+    }
+
+    fn resolve_name_without_aliases(&self,
+                                    name                : &String,
+                                    data_decryption_keys: DataEncryptionKeys) -> Result<ResolutionStep, ::errors::DnsError> {
+        let labels = try!(validation::split_name_labels(name));
+        if labels.len() < MIN_REGISTERED_NAME_LABELS {
+            return Err(::errors::DnsError::InvalidDnsName)
+        }
+
+        for split_at in 0..(labels.len() - MIN_REGISTERED_NAME_LABELS + 1) {
+            let candidate = labels[split_at..].join(".");
+            let struct_data = match try!(self.get_registered_structured_data(&candidate)) {
+                Some(struct_data) => struct_data,
+                None => continue,
+            };
+
+            debug!("Resolved {:?} to registered dns {:?} ...", name, candidate);
+            let mut remaining_labels = labels[..split_at].to_vec();
+            let dns_record = try!(self.read_dns_record(&struct_data, data_decryption_keys));
+            let (struct_data, mut dns_record) = try!(self.follow_forwarding(struct_data, dns_record, data_decryption_keys));
+            let mut visited = vec![struct_data.get_identifier().clone()];
+            let mut delegations = Vec::new();
+
+            loop {
+                if dns_record.is_expired() {
+                    return Err(::errors::DnsError::DnsRecordExpired)
+                }
+
+                if let Some(ref target) = dns_record.alias {
+                    remaining_labels.push(target.clone());
+                    return Ok(ResolutionStep::Aliased(remaining_labels.join(".")))
+                }
+
+                let next_delegation = remaining_labels.last().and_then(|label| dns_record.delegations.get(label)).cloned();
+                let delegation = match next_delegation {
+                    Some(delegation) => delegation,
+                    None => break,
+                };
+                if delegations.len() == MAX_DELEGATION_DEPTH {
+                    return Err(::errors::DnsError::DelegationDepthExceeded)
+                }
+                if visited.contains(&delegation.identifier) {
+                    return Err(::errors::DnsError::DelegationLoop)
+                }
+
+                let label = try!(remaining_labels.pop().ok_or(::errors::DnsError::from("Programming Error - Investigate !!")));
+                debug!("Following delegation of {:?} ...", label);
+                // Delegated records belong to someone else, hence are expected to be public
+                let (delegated_struct_data, delegated_dns_record) = try!(self.get_structured_data_and_dns_record(delegation.identifier.clone(),
+                                                                                                                 None));
+                if *delegated_struct_data.get_owner_keys() != delegation.owners {
+                    return Err(::errors::DnsError::DelegationOwnerMismatch)
+                }
+
+                visited.push(delegation.identifier);
+                delegations.push(label);
+                dns_record = delegated_dns_record;
+            }
+
+            let service_name = match remaining_labels.len() {
+                0 => None,
+                1 => Some(remaining_labels[0].clone()),
+                _ => return Err(::errors::DnsError::ServiceNotFound),
+            };
+            let default_service = dns_record.default_service.clone().unwrap_or(DEFAULT_SERVICE_NAME.to_string());
+            let looked_up_service_name = service_name.as_ref().unwrap_or(&default_service).clone();
+            let wildcard_label = if dns_record.service_links.contains_key(&looked_up_service_name) {
+                None
+            } else {
+                match dns_record.find_service(&looked_up_service_name) {
+                    Ok((_, label)) => label,
+                    // Absence of the service is reported when its target is asked for
+                    Err(_) => None,
+                }
+            };
+
+            return Ok(ResolutionStep::Resolved(Resolution {
+                long_name      : candidate,
+                service_name   : service_name,
+                default_service: default_service,
+                wildcard_label : wildcard_label,
+                delegations    : delegations,
+                aliases        : Vec::new(),
+                identifier     : try!(visited.pop().ok_or(::errors::DnsError::from("Programming Error - Investigate !!"))),
+            }))
+        }
+
+        Err(::errors::DnsError::DnsRecordNotFound)
+    }
+
+    /// Structured data housing the Dns record of the given name, `None` if the name is not
+    /// registered. Failures to reach the network are errors, so that a shorter suffix (possibly
+    /// owned by someone else) is never resolved in place of an unreachable registered name.
+    fn get_registered_structured_data(&self, long_name: &String) -> Result<Option<StructuredData>, ::errors::DnsError> {
+        match self.get_housing_structured_data(long_name) {
+            Ok(struct_data) => Ok(if struct_data.get_data().is_empty() {
+                None
+            } else {
+                Some(struct_data)
+            }),
+            Err(ref error) if super::storage::is_data_not_found(error) => Ok(None),
+            // No claim of the name checks out, in instances requiring commitments
+            Err(::errors::DnsError::DnsRecordNotFound) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+fn shuffle<T>(items: &mut Vec<T>) {
+    for i in (1..items.len()).rev() {
+        let random = ::sodiumoxide::randombytes::randombytes(4).iter().fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+        items.swap(i, random % (i + 1));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use routing::Data;
+    use xor_name::XorName;
+    use dns_operations::{Delegation, DnsOperations, DEFAULT_SERVICE_NAME};
+    use dns_operations::test_utils;
+
+    #[test]
+    fn resolve_multi_label_names() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let dns_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let (struct_data, _) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                        &messaging_keypair.0,
+                                                                        &messaging_keypair.1,
+                                                                        &vec![],
+                                                                        owners,
+                                                                        &secret_signing_key,
+                                                                        None));
+        unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+
+        let resolution = unwrap_result!(dns_operations.resolve_name(&dns_name, None));
+        assert_eq!(resolution.long_name, dns_name);
+        assert_eq!(resolution.service_name, None);
+        assert_eq!(*resolution.get_service_name(), DEFAULT_SERVICE_NAME.to_string());
+
+        let resolution = unwrap_result!(dns_operations.resolve_name(&("www.".to_string() + &dns_name), None));
+        assert_eq!(resolution.long_name, dns_name);
+        assert_eq!(resolution.service_name, Some("www".to_string()));
+
+        match dns_operations.resolve_name(&("docs.api.".to_string() + &dns_name), None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::ServiceNotFound) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+
+    #[test]
+    fn resolve_through_delegations() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let company_name = test_utils::generate_random_dns_name();
+        let team_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+
+        for &(ref name, ref services) in [(company_name.clone(), vec![]),
+                                          (team_name.clone(), vec![("www".to_string(), home_dir_key.clone())])].iter() {
+            let (struct_data, _) = unwrap_result!(dns_operations.register_dns(name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            services,
+                                                                            owners.clone(),
+                                                                            &secret_signing_key,
+                                                                            None));
+            unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        }
+
+        // Delegate team-a.<company> to the team's own record
+        let struct_data = unwrap_result!(dns_operations.add_delegation(&company_name,
+                                                                     ("team-a".to_string(),
+                                                                      Delegation::to_registered_name(&team_name, owners.clone())),
+                                                                     &secret_signing_key,
+                                                                     None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let resolution = unwrap_result!(dns_operations.resolve_name(&("www.team-a.".to_string() + &company_name), None));
+        assert_eq!(resolution.long_name, company_name);
+        assert_eq!(resolution.delegations, vec!["team-a".to_string()]);
+        assert_eq!(resolution.service_name, Some("www".to_string()));
+        assert_eq!(unwrap_result!(dns_operations.get_resolved_service_home_directory_key(&resolution, &"www".to_string(), None)),
+                   home_dir_key);
+
+        // Delegating back to the company record must be detected as a loop
+        let struct_data = unwrap_result!(dns_operations.add_delegation(&team_name,
+                                                                     ("back".to_string(),
+                                                                      Delegation::to_registered_name(&company_name, owners.clone())),
+                                                                     &secret_signing_key,
+                                                                     None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        match dns_operations.resolve_name(&("www.back.team-a.".to_string() + &company_name), None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::DelegationLoop) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+
+    #[test]
+    fn resolve_through_aliases() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(unwrap_result!(::safe_core::utility::test_utils::get_client())));
+        let dns_operations = unwrap_result!(DnsOperations::new(client.clone()));
+
+        let old_name = test_utils::generate_random_dns_name();
+        let new_name = test_utils::generate_random_dns_name();
+        let messaging_keypair = ::sodiumoxide::crypto::box_::gen_keypair();
+        let owners = vec![unwrap_result!(unwrap_result!(client.lock()).get_public_signing_key()).clone()];
+        let secret_signing_key = unwrap_result!(unwrap_result!(client.lock()).get_secret_signing_key()).clone();
+
+        let home_dir_key = ::safe_nfs::metadata::directory_key::DirectoryKey::new(XorName::new([123; 64]),
+                                                                                 15000,
+                                                                                 false,
+                                                                                 ::safe_nfs::AccessLevel::Public);
+
+        for &(ref name, ref services) in [(old_name.clone(), vec![]),
+                                          (new_name.clone(), vec![("www".to_string(), home_dir_key.clone())])].iter() {
+            let (struct_data, _) = unwrap_result!(dns_operations.register_dns(name.clone(),
+                                                                            &messaging_keypair.0,
+                                                                            &messaging_keypair.1,
+                                                                            services,
+                                                                            owners.clone(),
+                                                                            &secret_signing_key,
+                                                                            None));
+            unwrap_result!(unwrap_result!(client.lock()).put(Data::Structured(struct_data), None));
+        }
+
+        let struct_data = unwrap_result!(dns_operations.set_alias(&old_name, Some(new_name.clone()), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        let requested_name = "www.".to_string() + &old_name;
+        let resolution = unwrap_result!(dns_operations.resolve_name(&requested_name, None));
+        assert_eq!(resolution.long_name, new_name);
+        assert_eq!(resolution.service_name, Some("www".to_string()));
+        assert_eq!(resolution.aliases, vec![requested_name.clone(), "www.".to_string() + &new_name]);
+        assert_eq!(unwrap_result!(dns_operations.get_resolved_service_home_directory_key(&resolution, &"www".to_string(), None)),
+                   home_dir_key);
+
+        // Aliasing back to the old name must be detected as a loop
+        let struct_data = unwrap_result!(dns_operations.set_alias(&new_name, Some(old_name.clone()), &secret_signing_key, None));
+        unwrap_result!(unwrap_result!(client.lock()).post(Data::Structured(struct_data), None));
+
+        match dns_operations.resolve_name(&requested_name, None) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::AliasLoop) => (),
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use xor_name::XorName;
+use routing::{Data, DataRequest, ImmutableData, ImmutableDataType, StructuredData};
+
+const DNS_CONFIG_DIR_NAME: &'static str = "DnsReservedDirectory";
+
+/// Store the Dns records and the dns configuration of the user are kept in. Data that is absent
+/// from the store is reported as `DnsError::DataNotFound` (or as the `GetFailure` the network
+/// answers with by `SafeCoreStorage`).
+/// Structured data is only read by `DnsOperations`: that of Dns records, and the auxiliary
+/// structured data kept alongside, is returned by its operations for the caller to put, post or
+/// delete through the same storage. Written by `DnsOperations` itself are the dns configuration
+/// and the immutable data holding spilled services. Posted and deleted structured data is expected
+/// to be checked against the stored version, as the network does.
+pub trait Storage: Send + Sync {
+    /// Fetch the structured data of the given identifier and tag
+    fn get_structured_data(&self, identifier: XorName, tag: u64) -> Result<StructuredData, ::errors::DnsError>;
+
+    /// Store new structured data
+    fn put_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError>;
+
+    /// Replace stored structured data by its next version, signed by the owners of the stored one
+    fn post_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError>;
+
+    /// Remove stored structured data, given its next version signed by the owners of the stored one
+    fn delete_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError>;
+
+    /// Structured data of the given tag carrying the given data, encrypted if keys are given and
+    /// signed with the given key. Data too large to be carried directly may be stored separately.
+    fn create_structured_data(&self,
+                              tag                 : u64,
+                              identifier          : XorName,
+                              version             : u64,
+                              data                : Vec<u8>,
+                              owners              : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              prev_owners         : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              private_signing_key : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
+                                                            &::sodiumoxide::crypto::box_::SecretKey,
+                                                            &::sodiumoxide::crypto::box_::Nonce)>) -> Result<StructuredData, ::errors::DnsError>;
+
+    /// Data carried by structured data made through `create_structured_data`
+    fn get_structured_data_content(&self,
+                                   struct_data         : &StructuredData,
+                                   data_decryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
+                                                                 &::sodiumoxide::crypto::box_::SecretKey,
+                                                                 &::sodiumoxide::crypto::box_::Nonce)>) -> Result<Vec<u8>, ::errors::DnsError>;
+
+    /// Fetch the immutable data of the given name
+    fn get_immutable_data(&self, name: XorName) -> Result<ImmutableData, ::errors::DnsError>;
+
+    /// Store immutable data
+    fn put_immutable_data(&self, immutable_data: ImmutableData) -> Result<(), ::errors::DnsError>;
+
+    /// Fetch the listing of the directory services point to
+    fn get_directory_listing(&self,
+                             directory_key: &::safe_nfs::metadata::directory_key::DirectoryKey) -> Result<::safe_nfs::directory_listing::DirectoryListing,
+                                                                                                        ::errors::DnsError>;
+
+    /// Make sure the private configuration file of the given name exists, creating it empty if
+    /// needed
+    fn initialise_configuration(&self, file_name: &String) -> Result<(), ::errors::DnsError>;
+
+    /// Content of the private configuration file of the given name
+    fn read_configuration(&self, file_name: &String) -> Result<Vec<u8>, ::errors::DnsError>;
+
+    /// Replace the content of the private configuration file of the given name
+    fn write_configuration(&self, file_name: &String, content: Vec<u8>) -> Result<(), ::errors::DnsError>;
+}
+
+/// Whether the error reports the requested data as absent from the storage, rather than a failure
+/// to reach the storage at all
+pub fn is_data_not_found(error: &::errors::DnsError) -> bool {
+    match *error {
+        ::errors::DnsError::DataNotFound |
+        ::errors::DnsError::CoreError(::safe_core::errors::CoreError::GetFailure { .. }) => true,
+        _ => false,
+    }
+}
+
+/// Storage on the SAFE Network through a safe_core client, with the configuration kept in a
+/// configuration directory of the client
+pub struct SafeCoreStorage {
+    client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+}
+
+impl SafeCoreStorage {
+    /// Storage through the given client
+    pub fn new(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> SafeCoreStorage {
+        SafeCoreStorage {
+            client: client,
+        }
+    }
+
+    fn get_configuration_file(&self, file_name: &String) -> Result<(::safe_nfs::file::File,
+                                                                    ::safe_nfs::directory_listing::DirectoryListing), ::errors::DnsError> {
+        let dir_helper = ::safe_nfs::helper::directory_helper::DirectoryHelper::new(self.client.clone());
+        let dir_listing = try!(dir_helper.get_configuration_directory_listing(DNS_CONFIG_DIR_NAME.to_string()));
+        let file = try!(dir_listing.get_files().iter().find(|file| *file.get_name() == *file_name).ok_or(::errors::DnsError::DnsConfigFileNotFoundOrCorrupted)).clone();
+        Ok((file, dir_listing))
+    }
+}
+
+impl Storage for SafeCoreStorage {
+    fn get_structured_data(&self, identifier: XorName, tag: u64) -> Result<StructuredData, ::errors::DnsError> {
+        let request = DataRequest::Structured(identifier, tag);
+        let response_getter = try!(unwrap_result!(self.client.lock()).get(request, None));
+        if let Data::Structured(struct_data) = try!(response_getter.get()) {
+            Ok(struct_data)
+        } else {
+            Err(::errors::DnsError::from(::safe_core::errors::CoreError::ReceivedUnexpectedData))
+        }
+    }
+
+    fn put_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError> {
+        Ok(try!(unwrap_result!(self.client.lock()).put(Data::Structured(struct_data), None)))
+    }
+
+    fn post_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError> {
+        Ok(try!(unwrap_result!(self.client.lock()).post(Data::Structured(struct_data), None)))
+    }
+
+    fn delete_structured_data(&self, struct_data: StructuredData) -> Result<(), ::errors::DnsError> {
+        Ok(try!(unwrap_result!(self.client.lock()).delete(Data::Structured(struct_data), None)))
+    }
+
+    fn create_structured_data(&self,
+                              tag                 : u64,
+                              identifier          : XorName,
+                              version             : u64,
+                              data                : Vec<u8>,
+                              owners              : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              prev_owners         : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+                              private_signing_key : &::sodiumoxide::crypto::sign::SecretKey,
+                              data_encryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
+                                                            &::sodiumoxide::crypto::box_::SecretKey,
+                                                            &::sodiumoxide::crypto::box_::Nonce)>) -> Result<StructuredData, ::errors::DnsError> {
+        Ok(try!(::safe_core::structured_data_operations::unversioned::create(self.client.clone(),
+                                                                               tag,
+                                                                               identifier,
+                                                                               version,
+                                                                               data,
+                                                                               owners,
+                                                                               prev_owners,
+                                                                               private_signing_key,
+                                                                               data_encryption_keys)))
+    }
+
+    fn get_structured_data_content(&self,
+                                   struct_data         : &StructuredData,
+                                   data_decryption_keys: Option<(&::sodiumoxide::crypto::box_::PublicKey,
+                                                                 &::sodiumoxide::crypto::box_::SecretKey,
+                                                                 &::sodiumoxide::crypto::box_::Nonce)>) -> Result<Vec<u8>, ::errors::DnsError> {
+        Ok(try!(::safe_core::structured_data_operations::unversioned::get_data(self.client.clone(),
+                                                                                 struct_data,
+                                                                                 data_decryption_keys)))
+    }
+
+    fn get_immutable_data(&self, name: XorName) -> Result<ImmutableData, ::errors::DnsError> {
+        let request = DataRequest::Immutable(name, ImmutableDataType::Normal);
+        let response_getter = try!(unwrap_result!(self.client.lock()).get(request, None));
+        if let Data::Immutable(immutable_data) = try!(response_getter.get()) {
+            Ok(immutable_data)
+        } else {
+            Err(::errors::DnsError::from(::safe_core::errors::CoreError::ReceivedUnexpectedData))
+        }
+    }
+
+    fn put_immutable_data(&self, immutable_data: ImmutableData) -> Result<(), ::errors::DnsError> {
+        Ok(try!(unwrap_result!(self.client.lock()).put(Data::Immutable(immutable_data), None)))
+    }
+
+    fn get_directory_listing(&self,
+                             directory_key: &::safe_nfs::metadata::directory_key::DirectoryKey) -> Result<::safe_nfs::directory_listing::DirectoryListing,
+                                                                                                        ::errors::DnsError> {
+        let dir_helper = ::safe_nfs::helper::directory_helper::DirectoryHelper::new(self.client.clone());
+        Ok(try!(dir_helper.get(directory_key)))
+    }
+
+    fn initialise_configuration(&self, file_name: &String) -> Result<(), ::errors::DnsError> {
+        let dir_helper = ::safe_nfs::helper::directory_helper::DirectoryHelper::new(self.client.clone());
+        let dir_listing = try!(dir_helper.get_configuration_directory_listing(DNS_CONFIG_DIR_NAME.to_string()));
+        let file_helper = ::safe_nfs::helper::file_helper::FileHelper::new(self.client.clone());
+        match file_helper.create(file_name.clone(), vec![], dir_listing) {
+            Ok(writer) => {
+                let _ = try!(writer.close());
+                Ok(())
+            },
+            Err(::safe_nfs::errors::NfsError::FileAlreadyExistsWithSameName) => Ok(()),
+            Err(error) => Err(::errors::DnsError::from(error)),
+        }
+    }
+
+    fn read_configuration(&self, file_name: &String) -> Result<Vec<u8>, ::errors::DnsError> {
+        let (file, _) = try!(self.get_configuration_file(file_name));
+        let file_helper = ::safe_nfs::helper::file_helper::FileHelper::new(self.client.clone());
+        let mut reader = file_helper.read(&file);
+        let size = reader.size();
+        if size != 0 {
+            Ok(try!(reader.read(0, size)))
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    fn write_configuration(&self, file_name: &String, content: Vec<u8>) -> Result<(), ::errors::DnsError> {
+        let (file, dir_listing) = try!(self.get_configuration_file(file_name));
+        let file_helper = ::safe_nfs::helper::file_helper::FileHelper::new(self.client.clone());
+        let mut writer = try!(file_helper.update_content(file, ::safe_nfs::helper::writer::Mode::Overwrite, dir_listing));
+        writer.write(&content, 0);
+        let _ = try!(writer.close());
+        Ok(())
+    }
+}
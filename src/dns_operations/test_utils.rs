@@ -0,0 +1,69 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use routing::StructuredData;
+use super::DnsOperations;
+
+/// Random Dns-name with the least number of labels a registered name may have, all made up of
+/// lowercase ASCII letters, eg., `qvxkzmwapd.com`
+pub fn generate_random_dns_name() -> String {
+    let label: String = ::sodiumoxide::randombytes::randombytes(10).iter().map(|byte| (b'a' + byte % 26) as char).collect();
+    label + ".com"
+}
+
+/// Keys a name was registered with through `register_test_name`
+pub struct TestKeys {
+    pub messaging_keypair : (::sodiumoxide::crypto::box_::PublicKey, ::sodiumoxide::crypto::box_::SecretKey),
+    pub owners            : Vec<::sodiumoxide::crypto::sign::PublicKey>,
+    pub secret_signing_key: ::sodiumoxide::crypto::sign::SecretKey,
+}
+
+/// Register a random Dns-name without services, owned by a freshly generated signing key, and
+/// store it along with the auxiliary structured data in the storage of the instance
+pub fn register_test_name(dns_operations: &DnsOperations) -> (String, TestKeys) {
+    let dns_name = generate_random_dns_name();
+    let signing_keypair = ::sodiumoxide::crypto::sign::gen_keypair();
+    let keys = TestKeys {
+        messaging_keypair : ::sodiumoxide::crypto::box_::gen_keypair(),
+        owners            : vec![signing_keypair.0],
+        secret_signing_key: signing_keypair.1,
+    };
+
+    let (struct_data, auxiliary_struct_data) = unwrap_result!(dns_operations.register_dns(dns_name.clone(),
+                                                                                         &keys.messaging_keypair.0,
+                                                                                         &keys.messaging_keypair.1,
+                                                                                         &vec![],
+                                                                                         keys.owners.clone(),
+                                                                                         &keys.secret_signing_key,
+                                                                                         None));
+    unwrap_result!(dns_operations.get_storage().put_structured_data(struct_data));
+    publish_auxiliary_structured_data(dns_operations, auxiliary_struct_data);
+
+    (dns_name, keys)
+}
+
+/// Put or post, as their version requires, the auxiliary structured data returned along with a
+/// Dns record
+pub fn publish_auxiliary_structured_data(dns_operations: &DnsOperations, auxiliary_struct_data: Vec<StructuredData>) {
+    for struct_data in auxiliary_struct_data.into_iter() {
+        if struct_data.get_version() == 0 {
+            unwrap_result!(dns_operations.get_storage().put_structured_data(struct_data));
+        } else {
+            unwrap_result!(dns_operations.get_storage().post_structured_data(struct_data));
+        }
+    }
+}
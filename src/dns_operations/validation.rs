@@ -0,0 +1,284 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+use super::dns_record::{ServiceInfo, ServiceKind, ServiceTarget};
+
+/// Maximum length of a service name - same as that of a label in classic DNS
+pub const MAX_SERVICE_NAME_LENGTH: usize = 63;
+
+/// Maximum length of the key of a text record
+pub const MAX_TEXT_RECORD_KEY_LENGTH: usize = 63;
+
+/// Maximum length of the value of a text record
+pub const MAX_TEXT_RECORD_VALUE_LENGTH: usize = 1024;
+
+/// Maximum number of text records a Dns record can hold
+pub const MAX_TEXT_RECORDS: usize = 64;
+
+/// Maximum length of the description of a service
+pub const MAX_SERVICE_DESCRIPTION_LENGTH: usize = 1024;
+
+/// Maximum length of any of the short strings (kind, protocol version, content hint) describing a
+/// service
+pub const MAX_SERVICE_INFO_FIELD_LENGTH: usize = 128;
+
+/// Maximum number of content hints of a service
+pub const MAX_SERVICE_CONTENT_HINTS: usize = 16;
+
+/// Maximum serialised size of a Dns record. Structured data can not carry more than 100 KiB; some
+/// room is left for the encryption and encoding overhead.
+pub const MAX_DNS_RECORD_SIZE: usize = 100 * 1024 - 512;
+
+/// Scheme every SAFE url starts with
+pub const SAFE_URL_SCHEME: &'static str = "safe:";
+
+/// Maximum number of labels in a fully qualified name presented for resolution
+pub const MAX_NAME_LABELS: usize = 16;
+
+/// Service names kept free for addressing, through the url scheme, the parts of a Dns-record that
+/// are not services. These can not be added to a Dns-record:
+/// - `_dns`: the Dns-record itself
+/// - `_meta`: descriptive data about the record and its services
+/// - `_txt`: free-form text attached to the record
+pub const RESERVED_SERVICE_NAMES: [&'static str; 3] = ["_dns", "_meta", "_txt"];
+
+/// Check that a service name can be addressed through the `safe:<service>.<dns-name>` url scheme
+/// and is not one of the reserved names. Services are looked up by their exact name, so only
+/// lowercase names are accepted - `Www` and `www` could otherwise be two different services.
+pub fn validate_service_name(service_name: &String) -> Result<(), ::errors::DnsError> {
+    if service_name.is_empty() || service_name.len() > MAX_SERVICE_NAME_LENGTH {
+        return Err(::errors::DnsError::InvalidServiceName)
+    }
+
+    if service_name.chars().any(|c| c >= 'A' && c <= 'Z') {
+        return Err(::errors::DnsError::InvalidServiceName)
+    }
+
+    if !service_name.chars().all(is_valid_label_char) || service_name.starts_with('-') || service_name.ends_with('-') {
+        return Err(::errors::DnsError::InvalidServiceName)
+    }
+
+    if RESERVED_SERVICE_NAMES.iter().any(|reserved| *reserved == *service_name) {
+        return Err(::errors::DnsError::ReservedServiceName)
+    }
+
+    Ok(())
+}
+
+/// Split a fully qualified name, eg., docs.api.example.com, into its labels
+pub fn split_name_labels(name: &String) -> Result<Vec<String>, ::errors::DnsError> {
+    let labels: Vec<String> = name.split('.').map(|label| label.to_string()).collect();
+    if labels.len() > MAX_NAME_LABELS ||
+       labels.iter().any(|label| label.is_empty() || !label.chars().all(is_valid_label_char)) {
+        Err(::errors::DnsError::InvalidDnsName)
+    } else {
+        Ok(labels)
+    }
+}
+
+/// Check that a text record is within the size bounds and its key is printable without spaces
+pub fn validate_text_record(key: &String, value: &String) -> Result<(), ::errors::DnsError> {
+    if key.is_empty() || key.len() > MAX_TEXT_RECORD_KEY_LENGTH || value.len() > MAX_TEXT_RECORD_VALUE_LENGTH ||
+       !key.chars().all(|c| c > ' ' && c <= '~') {
+        Err(::errors::DnsError::InvalidTextRecord)
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that the metadata of a service is within the size bounds
+pub fn validate_service_info(info: &ServiceInfo) -> Result<(), ::errors::DnsError> {
+    let kind_too_long = match info.kind {
+        ServiceKind::Other(ref kind) => kind.len() > MAX_SERVICE_INFO_FIELD_LENGTH,
+        _ => false,
+    };
+    let protocol_version_too_long = info.protocol_version.as_ref().map_or(false, |version| version.len() > MAX_SERVICE_INFO_FIELD_LENGTH);
+
+    if kind_too_long || protocol_version_too_long || info.description.len() > MAX_SERVICE_DESCRIPTION_LENGTH ||
+       info.content_hints.len() > MAX_SERVICE_CONTENT_HINTS ||
+       info.content_hints.iter().any(|hint| hint.len() > MAX_SERVICE_INFO_FIELD_LENGTH) {
+        Err(::errors::DnsError::InvalidServiceInfo)
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that a service target can be stored in a Dns record
+pub fn validate_service_target(target: &ServiceTarget) -> Result<(), ::errors::DnsError> {
+    match *target {
+        ServiceTarget::Directory(_) | ServiceTarget::ImmutableData(_) => Ok(()),
+        ServiceTarget::Redirect(ref url) => validate_redirect_url(url),
+        ServiceTarget::File { ref file_name, .. } => validate_file_name(file_name),
+    }
+}
+
+/// Check that a file target names a single file within its directory, as NFS looks files up by
+/// name only - no path separators and no relative path components
+pub fn validate_file_name(file_name: &String) -> Result<(), ::errors::DnsError> {
+    if file_name.is_empty() || file_name == "." || file_name == ".." || file_name.contains('/') || file_name.contains('\\') {
+        Err(::errors::DnsError::InvalidServiceTarget)
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that a redirect target is a SAFE url, eg., `safe:www.newname.com/blog`
+pub fn validate_redirect_url(url: &String) -> Result<(), ::errors::DnsError> {
+    if url.len() <= SAFE_URL_SCHEME.len() || !url.starts_with(SAFE_URL_SCHEME) || url.chars().any(char::is_whitespace) {
+        Err(::errors::DnsError::InvalidRedirectUrl)
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that a Dns record of the given serialised size fits in a structured data
+pub fn validate_dns_record_size(size: usize) -> Result<(), ::errors::DnsError> {
+    if size > MAX_DNS_RECORD_SIZE {
+        Err(::errors::DnsError::DnsRecordTooLarge {
+            limit: MAX_DNS_RECORD_SIZE,
+            size : size,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+fn is_valid_label_char(c: char) -> bool {
+    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9') || c == '-' || c == '_'
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn service_name_validation() {
+        unwrap_result!(validate_service_name(&"www".to_string()));
+        unwrap_result!(validate_service_name(&"bad-ass".to_string()));
+        unwrap_result!(validate_service_name(&"micro_blog2".to_string()));
+
+        let invalid_names = vec!["".to_string(),
+                                 "blog.www".to_string(),
+                                 "my blog".to_string(),
+                                 " www".to_string(),
+                                 "-www".to_string(),
+                                 "www-".to_string(),
+                                 "Www".to_string(),
+                                 "_DNS".to_string(),
+                                 ::std::iter::repeat('a').take(MAX_SERVICE_NAME_LENGTH + 1).collect::<String>()];
+        for name in invalid_names.iter() {
+            match validate_service_name(name) {
+                Ok(_) => panic!("Should have been an error for {:?}", name),
+                Err(::errors::DnsError::InvalidServiceName) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+
+        for name in RESERVED_SERVICE_NAMES.iter() {
+            match validate_service_name(&name.to_string()) {
+                Ok(_) => panic!("Should have been an error for {:?}", name),
+                Err(::errors::DnsError::ReservedServiceName) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn redirect_url_validation() {
+        unwrap_result!(validate_redirect_url(&"safe:www.newname.com/blog".to_string()));
+
+        for url in vec!["safe:".to_string(), "http://newname.com".to_string(), "safe:new name.com".to_string()].iter() {
+            match validate_redirect_url(url) {
+                Ok(_) => panic!("Should have been an error for {:?}", url),
+                Err(::errors::DnsError::InvalidRedirectUrl) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn file_name_validation() {
+        unwrap_result!(validate_file_name(&"index.html".to_string()));
+        unwrap_result!(validate_file_name(&"..hidden".to_string()));
+
+        for file_name in vec!["".to_string(),
+                              ".".to_string(),
+                              "..".to_string(),
+                              "blog/index.html".to_string(),
+                              "../index.html".to_string(),
+                              "/index.html".to_string(),
+                              "blog\\index.html".to_string()].iter() {
+            match validate_file_name(file_name) {
+                Ok(_) => panic!("Should have been an error for {:?}", file_name),
+                Err(::errors::DnsError::InvalidServiceTarget) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn text_record_validation() {
+        unwrap_result!(validate_text_record(&"safe-verification".to_string(), &"a1b2c3".to_string()));
+        unwrap_result!(validate_text_record(&"contact".to_string(), &"".to_string()));
+
+        let too_long_value = ::std::iter::repeat('a').take(MAX_TEXT_RECORD_VALUE_LENGTH + 1).collect::<String>();
+        for &(ref key, ref value) in vec![("".to_string(), "value".to_string()),
+                                          ("my key".to_string(), "value".to_string()),
+                                          ("key".to_string(), too_long_value)].iter() {
+            match validate_text_record(key, value) {
+                Ok(_) => panic!("Should have been an error for {:?}", key),
+                Err(::errors::DnsError::InvalidTextRecord) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+    }
+
+    #[test]
+    fn dns_record_size_validation() {
+        unwrap_result!(validate_dns_record_size(0));
+        unwrap_result!(validate_dns_record_size(MAX_DNS_RECORD_SIZE));
+
+        match validate_dns_record_size(MAX_DNS_RECORD_SIZE + 1) {
+            Ok(_) => panic!("Should have been an error"),
+            Err(::errors::DnsError::DnsRecordTooLarge { limit, size }) => {
+                assert_eq!(limit, MAX_DNS_RECORD_SIZE);
+                assert_eq!(size, MAX_DNS_RECORD_SIZE + 1);
+            },
+            Err(error) => panic!("{:?}", error),
+        }
+    }
+
+    #[test]
+    fn name_label_splitting() {
+        let labels = unwrap_result!(split_name_labels(&"docs.api.example.com".to_string()));
+        assert_eq!(labels, vec!["docs".to_string(), "api".to_string(), "example".to_string(), "com".to_string()]);
+
+        let invalid_names = vec!["".to_string(),
+                                 "example..com".to_string(),
+                                 ".example.com".to_string(),
+                                 "example.com.".to_string(),
+                                 "exa mple.com".to_string(),
+                                 ::std::iter::repeat("a.").take(MAX_NAME_LABELS).collect::<String>() + "com"];
+        for name in invalid_names.iter() {
+            match split_name_labels(name) {
+                Ok(_) => panic!("Should have been an error for {:?}", name),
+                Err(::errors::DnsError::InvalidDnsName) => (),
+                Err(error) => panic!("{:?}", error),
+            }
+        }
+    }
+}
@@ -41,6 +41,85 @@ pub enum DnsError {
     Unexpected(String),
     /// Could not serialise or deserialise data
     UnsuccessfulEncodeDecode(SerialisationError),
+    /// Service name is empty, too long or contains characters not addressable through a Url
+    InvalidServiceName,
+    /// Service name is reserved for internal use
+    ReservedServiceName,
+    /// Dns name has empty labels, invalid characters or too many labels
+    InvalidDnsName,
+    /// Delegation already exists for the label
+    DelegationAlreadyExists,
+    /// Delegation not found
+    DelegationNotFound,
+    /// Following delegations led back to an already visited record
+    DelegationLoop,
+    /// Too many delegations had to be followed to resolve a name
+    DelegationDepthExceeded,
+    /// Delegated record is not owned by the owners named in the delegation
+    DelegationOwnerMismatch,
+    /// Following aliases led back to an already resolved name
+    AliasLoop,
+    /// Too many aliases had to be followed to resolve a name
+    AliasDepthExceeded,
+    /// Redirect target is not a SAFE url
+    InvalidRedirectUrl,
+    /// Service exists but does not point to a directory
+    ServiceIsNotADirectory,
+    /// Service target is malformed, eg., a file target without a file name or with a path as name
+    InvalidServiceTarget,
+    /// The file or data a service points to could not be found
+    ServiceTargetNotFound,
+    /// Text record already exists
+    TextRecordAlreadyExists,
+    /// Text record not found
+    TextRecordNotFound,
+    /// Text record key is malformed, value too large or too many text records
+    InvalidTextRecord,
+    /// Service metadata exceeds the size bounds
+    InvalidServiceInfo,
+    /// History limit is zero or exceeds the maximum number of entries kept
+    InvalidHistoryLimit,
+    /// Requested version is not kept in the history of the Dns record
+    HistoryVersionNotFound,
+    /// Serialised Dns record would not fit in a structured data
+    DnsRecordTooLarge {
+        /// Maximum serialised size allowed
+        limit: usize,
+        /// Serialised size of the record
+        size : usize,
+    },
+    /// Structured data housing a linked service is not owned by the owners the link expects
+    ServiceOwnerMismatch,
+    /// Service is housed in its own structured data and can only be edited by its owners
+    ServiceIsLinked,
+    /// Lease duration is zero or exceeds the maximum allowed
+    InvalidLeaseDuration,
+    /// Lease of the Dns record has run out
+    DnsRecordExpired,
+    /// Commitment to register the Dns-name is missing or owned by someone else
+    InvalidCommitment,
+    /// Commitment to register the Dns-name was made too recently to be revealed
+    CommitmentNotMatured,
+    /// All slots for claiming the Dns-name through a commitment are taken
+    CommitmentClaimsFull,
+    /// Public index of the names owned by a key would exceed the size a structured data can carry
+    OwnerIndexTooLarge,
+    /// Page size for searching a name directory is zero or too large
+    InvalidPageSize,
+    /// Name directory has no free slot for the Dns-name within the reads a search may make
+    NameDirectoryFull,
+    /// Dns-name is too long to be listed in a name directory
+    ListedNameTooLong,
+    /// Name of a namespace other than the default one is empty
+    InvalidNamespaceName,
+    /// Tag of a namespace is one used by structured data other than Dns records
+    ReservedNamespaceTag,
+    /// Dns record has been migrated to another identifier, see `DnsOperations::migrate_dns`
+    DnsRecordMoved,
+    /// Migrated Dns record is forwarded to structured data owned by someone else
+    ForwardingOwnerMismatch,
+    /// Requested data is absent from a storage other than the network
+    DataNotFound,
 }
 
 impl From<SerialisationError> for DnsError {
@@ -78,6 +157,43 @@ impl Into<i32> for DnsError {
             DnsError::DnsConfigFileNotFoundOrCorrupted => DNS_ERROR_START_RANGE - 4,
             DnsError::Unexpected(_)                    => DNS_ERROR_START_RANGE - 5,
             DnsError::UnsuccessfulEncodeDecode(_)      => DNS_ERROR_START_RANGE - 6,
+            DnsError::InvalidServiceName               => DNS_ERROR_START_RANGE - 7,
+            DnsError::ReservedServiceName              => DNS_ERROR_START_RANGE - 8,
+            DnsError::InvalidDnsName                   => DNS_ERROR_START_RANGE - 9,
+            DnsError::DelegationAlreadyExists          => DNS_ERROR_START_RANGE - 10,
+            DnsError::DelegationNotFound               => DNS_ERROR_START_RANGE - 11,
+            DnsError::DelegationLoop                   => DNS_ERROR_START_RANGE - 12,
+            DnsError::DelegationDepthExceeded          => DNS_ERROR_START_RANGE - 13,
+            DnsError::DelegationOwnerMismatch          => DNS_ERROR_START_RANGE - 14,
+            DnsError::AliasLoop                        => DNS_ERROR_START_RANGE - 15,
+            DnsError::AliasDepthExceeded               => DNS_ERROR_START_RANGE - 16,
+            DnsError::InvalidRedirectUrl               => DNS_ERROR_START_RANGE - 17,
+            DnsError::ServiceIsNotADirectory           => DNS_ERROR_START_RANGE - 18,
+            DnsError::InvalidServiceTarget             => DNS_ERROR_START_RANGE - 19,
+            DnsError::ServiceTargetNotFound            => DNS_ERROR_START_RANGE - 20,
+            DnsError::TextRecordAlreadyExists          => DNS_ERROR_START_RANGE - 21,
+            DnsError::TextRecordNotFound               => DNS_ERROR_START_RANGE - 22,
+            DnsError::InvalidTextRecord                => DNS_ERROR_START_RANGE - 23,
+            DnsError::InvalidServiceInfo               => DNS_ERROR_START_RANGE - 24,
+            DnsError::InvalidHistoryLimit              => DNS_ERROR_START_RANGE - 25,
+            DnsError::HistoryVersionNotFound           => DNS_ERROR_START_RANGE - 26,
+            DnsError::DnsRecordTooLarge { .. }         => DNS_ERROR_START_RANGE - 27,
+            DnsError::ServiceOwnerMismatch             => DNS_ERROR_START_RANGE - 28,
+            DnsError::ServiceIsLinked                  => DNS_ERROR_START_RANGE - 29,
+            DnsError::InvalidLeaseDuration             => DNS_ERROR_START_RANGE - 30,
+            DnsError::DnsRecordExpired                 => DNS_ERROR_START_RANGE - 31,
+            DnsError::InvalidCommitment                => DNS_ERROR_START_RANGE - 32,
+            DnsError::CommitmentNotMatured             => DNS_ERROR_START_RANGE - 33,
+            DnsError::CommitmentClaimsFull             => DNS_ERROR_START_RANGE - 34,
+            DnsError::OwnerIndexTooLarge               => DNS_ERROR_START_RANGE - 35,
+            DnsError::InvalidPageSize                  => DNS_ERROR_START_RANGE - 36,
+            DnsError::NameDirectoryFull                => DNS_ERROR_START_RANGE - 37,
+            DnsError::ListedNameTooLong                => DNS_ERROR_START_RANGE - 38,
+            DnsError::InvalidNamespaceName             => DNS_ERROR_START_RANGE - 39,
+            DnsError::ReservedNamespaceTag             => DNS_ERROR_START_RANGE - 40,
+            DnsError::DnsRecordMoved                   => DNS_ERROR_START_RANGE - 41,
+            DnsError::ForwardingOwnerMismatch          => DNS_ERROR_START_RANGE - 42,
+            DnsError::DataNotFound                     => DNS_ERROR_START_RANGE - 43,
         }
     }
 }
@@ -94,6 +210,43 @@ impl ::std::fmt::Debug for DnsError {
             DnsError::DnsConfigFileNotFoundOrCorrupted  => write!(f, "DnsError::DnsConfigFileNotFoundOrCorrupted"),
             DnsError::Unexpected(ref error)             => write!(f, "DnsError::Unexpected::{{{:?}}}", error),
             DnsError::UnsuccessfulEncodeDecode(ref err) => write!(f, "DnsError::UnsuccessfulEncodeDecode -> {:?}", err),
+            DnsError::InvalidServiceName                => write!(f, "DnsError::InvalidServiceName"),
+            DnsError::ReservedServiceName               => write!(f, "DnsError::ReservedServiceName"),
+            DnsError::InvalidDnsName                    => write!(f, "DnsError::InvalidDnsName"),
+            DnsError::DelegationAlreadyExists           => write!(f, "DnsError::DelegationAlreadyExists"),
+            DnsError::DelegationNotFound                => write!(f, "DnsError::DelegationNotFound"),
+            DnsError::DelegationLoop                    => write!(f, "DnsError::DelegationLoop"),
+            DnsError::DelegationDepthExceeded           => write!(f, "DnsError::DelegationDepthExceeded"),
+            DnsError::DelegationOwnerMismatch           => write!(f, "DnsError::DelegationOwnerMismatch"),
+            DnsError::AliasLoop                         => write!(f, "DnsError::AliasLoop"),
+            DnsError::AliasDepthExceeded                => write!(f, "DnsError::AliasDepthExceeded"),
+            DnsError::InvalidRedirectUrl                => write!(f, "DnsError::InvalidRedirectUrl"),
+            DnsError::ServiceIsNotADirectory            => write!(f, "DnsError::ServiceIsNotADirectory"),
+            DnsError::InvalidServiceTarget              => write!(f, "DnsError::InvalidServiceTarget"),
+            DnsError::ServiceTargetNotFound             => write!(f, "DnsError::ServiceTargetNotFound"),
+            DnsError::TextRecordAlreadyExists           => write!(f, "DnsError::TextRecordAlreadyExists"),
+            DnsError::TextRecordNotFound                => write!(f, "DnsError::TextRecordNotFound"),
+            DnsError::InvalidTextRecord                 => write!(f, "DnsError::InvalidTextRecord"),
+            DnsError::InvalidServiceInfo                => write!(f, "DnsError::InvalidServiceInfo"),
+            DnsError::InvalidHistoryLimit               => write!(f, "DnsError::InvalidHistoryLimit"),
+            DnsError::HistoryVersionNotFound            => write!(f, "DnsError::HistoryVersionNotFound"),
+            DnsError::DnsRecordTooLarge { limit, size } => write!(f, "DnsError::DnsRecordTooLarge {{ limit: {}, size: {} }}", limit, size),
+            DnsError::ServiceOwnerMismatch              => write!(f, "DnsError::ServiceOwnerMismatch"),
+            DnsError::ServiceIsLinked                   => write!(f, "DnsError::ServiceIsLinked"),
+            DnsError::InvalidLeaseDuration              => write!(f, "DnsError::InvalidLeaseDuration"),
+            DnsError::DnsRecordExpired                  => write!(f, "DnsError::DnsRecordExpired"),
+            DnsError::InvalidCommitment                 => write!(f, "DnsError::InvalidCommitment"),
+            DnsError::CommitmentNotMatured              => write!(f, "DnsError::CommitmentNotMatured"),
+            DnsError::CommitmentClaimsFull              => write!(f, "DnsError::CommitmentClaimsFull"),
+            DnsError::OwnerIndexTooLarge                => write!(f, "DnsError::OwnerIndexTooLarge"),
+            DnsError::InvalidPageSize                   => write!(f, "DnsError::InvalidPageSize"),
+            DnsError::NameDirectoryFull                 => write!(f, "DnsError::NameDirectoryFull"),
+            DnsError::ListedNameTooLong                 => write!(f, "DnsError::ListedNameTooLong"),
+            DnsError::InvalidNamespaceName              => write!(f, "DnsError::InvalidNamespaceName"),
+            DnsError::ReservedNamespaceTag              => write!(f, "DnsError::ReservedNamespaceTag"),
+            DnsError::DnsRecordMoved                    => write!(f, "DnsError::DnsRecordMoved"),
+            DnsError::ForwardingOwnerMismatch           => write!(f, "DnsError::ForwardingOwnerMismatch"),
+            DnsError::DataNotFound                      => write!(f, "DnsError::DataNotFound"),
         }
     }
 }